@@ -1,12 +1,24 @@
 use emas_rs::SystemBuilder;
 use emas_rs::fitness_functions::{RastriginFitness, FitnessFn};
+use emas_rs::log_sinks::CsvLog;
+use std::path::Path;
 use std::time::Instant;
 
 fn main() {
     const N: usize = 100;
-    let mut system = SystemBuilder::<N, RastriginFitness<N>>::new().steps(1_000_000).build();
+    const CHECKPOINT: &str = "rastrigin_checkpoint.json";
+    let mut system = SystemBuilder::<RastriginFitness<N>>::new()
+        .steps(1_000_000)
+        .target_fitness(1e-3)
+        .checkpoint(CHECKPOINT, 10_000)
+        .log_sink(CsvLog::create("outputs.csv").expect("Can't create the log file"))
+        .build();
+    if Path::new(CHECKPOINT).exists() {
+        system.load_checkpoint(CHECKPOINT).expect("Can't load the checkpoint");
+    }
     let t0 = Instant::now();
-    let sol = system.run();
+    let result = system.run();
+    let sol = result.best_sol;
     let t0 = t0.elapsed().as_secs_f32();
 
     print!("[");
@@ -19,6 +31,6 @@ fn main() {
             print!(", {}", arg);
         }
     }
-    println!("] => {}", RastriginFitness::call(&sol));
-    println!("{}s", t0);
+    println!("] => {}", RastriginFitness::<N>::default().call(&sol));
+    println!("{}s, stopped after {} steps: {:?}", t0, result.steps, result.termination);
 }
\ No newline at end of file
@@ -0,0 +1,24 @@
+use emas_rs::SystemBuilder;
+use emas_rs::fitness_functions::{from_fn, FitnessFn};
+use std::env;
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+fn main() {
+    let n: usize = env::args()
+        .nth(1)
+        .map(|arg| arg.parse().expect("The dimension has to be a number"))
+        .unwrap_or(10);
+
+    let rastrigin = Arc::new(from_fn(vec![(-5.12, 5.12); n], |args: &Vec<f64>| {
+        10.0 * args.len() as f64
+            + args
+                .iter()
+                .map(|xi| xi.powi(2) - 10.0 * (2.0 * PI * xi).cos())
+                .sum::<f64>()
+    }));
+
+    let mut system = SystemBuilder::<_>::with_fitness(rastrigin.clone()).steps(10_000).build();
+    let sol = system.run().best_sol;
+    println!("{:?} => {}", sol, rastrigin.call(&sol));
+}
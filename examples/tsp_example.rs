@@ -0,0 +1,19 @@
+use emas_rs::SystemBuilder;
+use emas_rs::fitness_functions::FitnessFn;
+use emas_rs::tsp::TspFitness;
+use std::env;
+
+/// Solves the TSPLIB instance given as the argument, e.g. `berlin52.tsp`.
+fn main() {
+    let path = env::args().nth(1).expect("Pass the path to a TSPLIB .tsp file");
+    let tsp = TspFitness::from_tsplib(&path).expect("Can't load the instance");
+    println!("{}: {} cities", tsp.name, tsp.cities_amount());
+
+    let mut system = SystemBuilder::<TspFitness>::with_fitness(tsp.clone())
+        .steps(20_000)
+        .stagnation_steps(5_000)
+        .build();
+    let result = system.run();
+    println!("{:?}", result.best_sol.0);
+    println!("length {} after {} steps: {:?}", tsp.call(&result.best_sol), result.steps, result.termination);
+}
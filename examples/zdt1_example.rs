@@ -0,0 +1,44 @@
+use emas_rs::SystemBuilder;
+use emas_rs::conf_functions::ParetoCombatWinChanceFn;
+use emas_rs::fitness_functions::FitnessFn;
+use emas_rs::log_sinks::MemoryLog;
+
+/// The ZDT1 benchmark, whose Pareto front is `f2 = 1 - sqrt(f1)` for `f1` in `[0, 1]`.
+#[derive(Default)]
+struct Zdt1Fitness {}
+
+impl FitnessFn for Zdt1Fitness {
+    type Genome = [f64; 30];
+
+    fn domain(&self) -> [(f64, f64); 30] {
+        [(0.0, 1.0); 30]
+    }
+
+    fn call(&self, args: &[f64; 30]) -> f64 {
+        self.objectives(args).iter().sum()
+    }
+
+    fn objectives(&self, args: &[f64; 30]) -> Vec<f64> {
+        let f1 = args[0];
+        let g = 1.0 + 9.0 * args[1..].iter().sum::<f64>() / 29.0;
+        let f2 = g * (1.0 - (f1 / g).sqrt());
+        vec![f1, f2]
+    }
+}
+
+fn main() {
+    let log = MemoryLog::new();
+    let mut system = SystemBuilder::<Zdt1Fitness, ParetoCombatWinChanceFn>::new()
+        .steps(5_000)
+        .pareto(vec![1.1, 1.1])
+        .log_sink(log.clone())
+        .build();
+    let mut front = system.run().pareto_front;
+    front.sort_by(|s1, s2| s1.objectives[0].partial_cmp(&s2.objectives[0]).unwrap());
+
+    for solution in &front {
+        println!("{:.4} {:.4}", solution.objectives[0], solution.objectives[1]);
+    }
+    let last = log.records().pop().unwrap();
+    println!("{} solutions, hypervolume {:?}", front.len(), last.hypervolume);
+}
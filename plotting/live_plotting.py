@@ -1,16 +1,20 @@
+import sys
 import pandas as pd
 import matplotlib.pyplot as plt
 from matplotlib.animation import FuncAnimation
 import matplotlib as mpl
 mpl.use('tkagg')
 
+# The CSV written by CsvLog, e.g. `.log_sink(CsvLog::create("outputs.csv")?)`.
+path = sys.argv[1] if len(sys.argv) > 1 else './outputs.csv'
+
 plt.style.use('fivethirtyeight')
 figure, axis = plt.subplots(2, 2)
 figure.set_size_inches(9.5, 6.5)
 figure.set_dpi(100)
 
 def animate(i):
-    data = pd.read_csv('./outputs.csv')
+    data = pd.read_csv(path)
     xs = data['timestamp']
 
     axis[0, 0].clear()
@@ -0,0 +1,60 @@
+use rand::RngCore;
+use std::fmt::Debug;
+
+/// What an agent does in a step.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    /// Reproduces with another agent which chose to reproduce.
+    Reproduce,
+    /// Fights another agent which chose to fight.
+    Combat,
+    /// Spends energy on the local search, see
+    /// [`SystemBuilder::local_search`](crate::SystemBuilder::local_search). The agent rests
+    /// instead if it can't afford the search. Choosing it in a system which isn't memetic is a
+    /// configuration error, the system panics.
+    LocalSearch,
+    /// Leaves its island for a neighbouring one in the migration topology. The agent arrives
+    /// there at the end of the step.
+    Migrate,
+    /// Does nothing.
+    Rest,
+    /// A user-defined action, performed by [`ActionSelectionFn::custom`].
+    Custom(u32),
+}
+
+/// What an agent knows about itself when choosing its action.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AgentState {
+    pub energy: u32,
+    /// The fitness as minimized by the system, see
+    /// [`Combatant::fitness`](crate::conf_functions::Combatant::fitness).
+    pub fitness: f64,
+    /// The amount of steps since the agent was born.
+    pub age: u32,
+    /// The amount of the combats won.
+    pub prestige: u32,
+}
+
+/// The state of the island of an agent choosing its action, as at the start of the step.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IslandState {
+    pub island: usize,
+    pub step: u32,
+    pub agents_amount: usize,
+    pub energy_sum: u32,
+    /// The best fitness of the living agents, minimized like the fitness of the agent.
+    pub best_fitness: f64,
+    pub average_fitness: f64,
+    pub average_energy: f64,
+}
+
+/// Chooses the action of every agent in every step, see
+/// [`SystemBuilder::action_selection`](crate::SystemBuilder::action_selection).
+pub trait ActionSelectionFn: Debug + Send + Sync {
+    fn call(&self, agent: &AgentState, island: &IslandState, rng: &mut dyn RngCore) -> Action;
+
+    /// Performs the [`Action::Custom`] actions, which may change the energy of the agent. The
+    /// custom actions are performed after the reproductions and before the combats. Does
+    /// nothing by default.
+    fn custom(&self, _action: u32, _agent: &AgentState, _energy: &mut u32, _rng: &mut dyn RngCore) {}
+}
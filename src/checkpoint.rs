@@ -0,0 +1,74 @@
+use crate::energy::EnergyFlows;
+use crate::genome::Genome;
+use crate::pareto::ParetoArchive;
+use crate::Agent;
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// The state of a [`System`](crate::System) at a synchronisation point. The configuration, the
+/// fitness function and the operators aren't saved, the state is loaded into a system built with
+/// the same builder.
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+pub(crate) struct Checkpoint<G: Genome> {
+    /// The next step to be made.
+    pub(crate) step: u32,
+    pub(crate) elapsed: Duration,
+    pub(crate) last_improvement: u32,
+    pub(crate) rng: ChaCha8Rng,
+    /// The neighbours of every island, saved as a random topology is drawn when the system is
+    /// built.
+    pub(crate) migration_targets: Vec<Vec<usize>>,
+    pub(crate) islands: Vec<IslandCheckpoint<G>>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+pub(crate) struct IslandCheckpoint<G: Genome> {
+    pub(crate) agents: Vec<Agent<G>>,
+    pub(crate) migration_queue: Vec<Agent<G>>,
+    pub(crate) last_agent_id: usize,
+    pub(crate) historical_best: Option<Agent<G>>,
+    pub(crate) best_feasible: Option<Agent<G>>,
+    pub(crate) archive: Option<ParetoArchive<G>>,
+    pub(crate) evaluations: u64,
+    pub(crate) local_search_evaluations: u64,
+    pub(crate) energy_flows: Option<EnergyFlows>,
+    pub(crate) rng: ChaCha8Rng,
+}
+
+/// Where and how often the checkpoints are written during a run.
+#[derive(Debug, Clone)]
+pub(crate) struct CheckpointConf {
+    pub(crate) path: PathBuf,
+    pub(crate) steps: u32,
+}
+
+/// Saves an `f64` as its bits, as JSON has no infinities nor NaNs, which are valid fitness
+/// values. Used with `#[serde(with = "f64_bits")]`.
+pub(crate) mod f64_bits {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub(crate) fn serialize<S: Serializer>(value: &f64, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(value.to_bits())
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+        u64::deserialize(deserializer).map(f64::from_bits)
+    }
+}
+
+/// Like [`f64_bits`], for the objectives.
+pub(crate) mod f64_vec_bits {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub(crate) fn serialize<S: Serializer>(values: &[f64], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(values.iter().map(|value| value.to_bits()))
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<f64>, D::Error> {
+        Vec::<u64>::deserialize(deserializer).map(|bits| bits.into_iter().map(f64::from_bits).collect())
+    }
+}
@@ -1,32 +1,100 @@
+use crate::pareto::dominates;
 use crate::ReproductionChance;
 
-pub trait CombatWinChanceFn {
-    fn call(this_agent_fitness: f64, other_agent_fitness: f64) -> f64;
+/// What is known about an agent taking part in a fight.
+#[derive(Debug, Clone, Copy)]
+pub struct Combatant<'a> {
+    /// The fitness as minimized by the system, i.e. negated if the system maximizes, so the
+    /// lower is always the better. See [`Direction::minimized`](crate::fitness_functions::Direction::minimized).
+    pub fitness: f64,
+    /// The objectives of a multi-objective problem, just the fitness otherwise. Minimized like
+    /// the fitness.
+    pub objectives: &'a [f64],
+    /// The crowding distance of the agent in the objective space, among the agents of its
+    /// island. Always `0.0` unless the problem is multi-objective.
+    pub crowding_distance: f64,
+    /// The violation of the constraints, or `0.0` if the agent is feasible or the violation is
+    /// already accounted for in a penalized fitness.
+    pub violation: f64,
+}
+
+impl Combatant<'_> {
+    /// Deb's feasibility rules: the less violating agent is better, the fitness only decides
+    /// between equally violating ones.
+    pub fn is_better_than(&self, other: &Combatant) -> bool {
+        if self.violation != other.violation {
+            return self.violation < other.violation;
+        }
+        self.fitness < other.fitness
+    }
+}
+
+pub trait CombatWinChanceFn: Send + Sync {
+    fn call(this_agent: &Combatant, other_agent: &Combatant) -> f64;
 }
 
 pub struct DefaultCombatWinChanceFn;
 
 impl CombatWinChanceFn for DefaultCombatWinChanceFn {
-    fn call(this_agent_fitness: f64, other_agent_fitness: f64) -> f64 {
-        if this_agent_fitness < other_agent_fitness {
+    fn call(this_agent: &Combatant, other_agent: &Combatant) -> f64 {
+        if this_agent.is_better_than(other_agent) {
             return 0.8;
         }
         0.2
     }
 }
 
-pub trait ReproductionChanceFn {
+/// The agent which Pareto dominates the other one is more likely to win. If neither of them
+/// dominates, the one from the less crowded part of the objective space is. The less violating
+/// agent wins more likely regardless of the objectives.
+pub struct ParetoCombatWinChanceFn;
+
+impl CombatWinChanceFn for ParetoCombatWinChanceFn {
+    fn call(this_agent: &Combatant, other_agent: &Combatant) -> f64 {
+        if this_agent.violation != other_agent.violation {
+            return if this_agent.violation < other_agent.violation { 0.8 } else { 0.2 };
+        }
+        if dominates(this_agent.objectives, other_agent.objectives) {
+            return 0.8;
+        }
+        if dominates(other_agent.objectives, this_agent.objectives) {
+            return 0.2;
+        }
+        if this_agent.crowding_distance > other_agent.crowding_distance {
+            return 0.8;
+        }
+        if this_agent.crowding_distance < other_agent.crowding_distance {
+            return 0.2;
+        }
+        0.5
+    }
+}
+
+/// The chance that an agent reproduces in a step, given its energy.
+pub trait ReproductionChanceFn: Send + Sync {
     fn call(energy: u32) -> ReproductionChance;
+
+    /// The chance given the reproduction threshold of the
+    /// [`EnergyPolicy`](crate::energy::EnergyPolicy), which is what the system calls for the
+    /// agents with at least the threshold energy. Calls `call` by default.
+    fn with_threshold(energy: u32, _threshold: u32) -> ReproductionChance {
+        Self::call(energy)
+    }
 }
 
 pub struct DefaultReproductionChanceFn;
 
 impl ReproductionChanceFn for DefaultReproductionChanceFn {
     fn call(energy: u32) -> ReproductionChance {
-        if energy < 25 {
+        Self::with_threshold(energy, 25)
+    }
+
+    /// The agents reproduce sometimes from the threshold on and always from twice the threshold.
+    fn with_threshold(energy: u32, threshold: u32) -> ReproductionChance {
+        if energy < threshold {
             return ReproductionChance(0.0);
         }
-        if energy < 50 {
+        if energy < 2 * threshold {
             return ReproductionChance(0.25);
         }
         ReproductionChance(1.0)
@@ -0,0 +1,115 @@
+use crate::genome::Genome;
+use rand::RngCore;
+use std::cmp::Ordering;
+use std::fmt::{Debug, Formatter};
+
+/// The value of a constraint function for a solution, see [`FitnessFn::constraints`](crate::fitness_functions::FitnessFn::constraints).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Constraint {
+    /// Satisfied when the value is at most zero.
+    Inequality(f64),
+    /// Satisfied when the value is zero, up to the tolerance set with
+    /// [`SystemBuilder::equality_tolerance`](crate::SystemBuilder::equality_tolerance).
+    Equality(f64),
+}
+
+/// The total amount by which the constraints are violated, zero if the solution is feasible.
+pub fn violation(constraints: &[Constraint], equality_tolerance: f64) -> f64 {
+    constraints
+        .iter()
+        .map(|constraint| match *constraint {
+            Constraint::Inequality(g) => g.max(0.0),
+            Constraint::Equality(h) => (h.abs() - equality_tolerance).max(0.0),
+        })
+        .sum()
+}
+
+/// How the violation of the constraints is taken into account when the agents are compared, in
+/// the combats and when looking for the best solution.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ConstraintHandling {
+    /// The fitness is increased by `coefficient * violation`.
+    StaticPenalty { coefficient: f64 },
+    /// The fitness is increased by `(c * step)^alpha * violation`, so the infeasible agents are
+    /// tolerated early in the run and driven out later on.
+    DynamicPenalty { c: f64, alpha: f64 },
+    /// Deb's feasibility rules: a feasible agent is better than an infeasible one, of two
+    /// infeasible agents the less violating one is better and only two feasible agents are
+    /// compared by fitness.
+    #[default]
+    FeasibilityRules,
+}
+
+impl ConstraintHandling {
+    /// The fitness and the violation the agent is compared by at the given step.
+    pub(crate) fn assess(&self, fitness: f64, violation: f64, step: u32) -> (f64, f64) {
+        match *self {
+            ConstraintHandling::StaticPenalty { coefficient } => (fitness + coefficient * violation, 0.0),
+            ConstraintHandling::DynamicPenalty { c, alpha } => {
+                (fitness + (c * step as f64).powf(alpha) * violation, 0.0)
+            }
+            ConstraintHandling::FeasibilityRules => (fitness, violation),
+        }
+    }
+
+    /// Orders two pairs of the fitness and the violation from the better one, as they are
+    /// assessed at the given step.
+    pub(crate) fn compare(&self, (f1, v1): (f64, f64), (f2, v2): (f64, f64), step: u32) -> Ordering {
+        let (f1, v1) = self.assess(f1, v1, step);
+        let (f2, v2) = self.assess(f2, v2, step);
+        v1.total_cmp(&v2).then(f1.total_cmp(&f2))
+    }
+}
+
+/// Moves the genotype of a new agent towards the feasible region, before it is evaluated. The
+/// repaired genes are the ones the agent lives with.
+pub trait RepairFn<G: Genome>: Debug + Send + Sync {
+    fn call(&self, genes: &mut G, domain: &G::Domain, rng: &mut dyn RngCore);
+}
+
+/// A repair operator made of a closure, see [`from_fn`].
+pub struct FnRepair<C>(C);
+
+impl<C> Debug for FnRepair<C> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FnRepair").finish_non_exhaustive()
+    }
+}
+
+impl<G: Genome, C> RepairFn<G> for FnRepair<C>
+    where
+        C: Fn(&mut G) + Send + Sync
+{
+    fn call(&self, genes: &mut G, _domain: &G::Domain, _rng: &mut dyn RngCore) {
+        (self.0)(genes)
+    }
+}
+
+/// Creates a repair operator from a closure.
+pub fn from_fn<G: Genome, C>(f: C) -> FnRepair<C>
+    where
+        C: Fn(&mut G) + Send + Sync
+{
+    FnRepair(f)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::constraints::*;
+
+    #[test]
+    fn violation_test() {
+        let constraints = [Constraint::Inequality(-1.0), Constraint::Inequality(2.0), Constraint::Equality(-0.5)];
+        assert_eq!(violation(&constraints, 0.0), 2.5);
+        assert_eq!(violation(&constraints, 0.5), 2.0);
+        assert_eq!(violation(&[Constraint::Inequality(0.0), Constraint::Equality(0.1)], 0.1), 0.0);
+
+        assert_eq!(ConstraintHandling::StaticPenalty { coefficient: 10.0 }.assess(1.0, 0.5, 7), (6.0, 0.0));
+        assert_eq!(ConstraintHandling::DynamicPenalty { c: 0.5, alpha: 2.0 }.assess(1.0, 0.5, 4), (3.0, 0.0));
+        assert_eq!(ConstraintHandling::FeasibilityRules.assess(1.0, 0.5, 4), (1.0, 0.5));
+
+        assert!(ConstraintHandling::FeasibilityRules.compare((5.0, 0.0), (1.0, 0.5), 4).is_lt());
+        let penalty = ConstraintHandling::StaticPenalty { coefficient: 1.0 };
+        assert!(penalty.compare((5.0, 0.0), (1.0, 0.5), 4).is_gt());
+    }
+}
@@ -0,0 +1,212 @@
+use serde::{Deserialize, Serialize};
+
+/// The energy moved by every kind of transfer, see
+/// [`SystemBuilder::energy_ledger`](crate::SystemBuilder::energy_ledger).
+///
+/// Reproductions, combats and migrations move energy between the agents, the other transfers
+/// take it out of the system or bring it in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct EnergyFlows {
+    /// Passed from the parents to their children.
+    pub reproduction: u64,
+    /// Taken by the winners from the losers.
+    pub combat: u64,
+    /// Left by the agents when they died.
+    pub death: u64,
+    /// Carried by the agents leaving their islands, including the ones promoted to the elite
+    /// island.
+    pub migration: u64,
+    /// Spent on the local search.
+    pub local_search: u64,
+    /// Gained in the custom actions.
+    pub custom_gained: u64,
+    /// Lost in the custom actions.
+    pub custom_lost: u64,
+}
+
+impl EnergyFlows {
+    fn add(&mut self, other: &EnergyFlows) {
+        self.reproduction += other.reproduction;
+        self.combat += other.combat;
+        self.death += other.death;
+        self.migration += other.migration;
+        self.local_search += other.local_search;
+        self.custom_gained += other.custom_gained;
+        self.custom_lost += other.custom_lost;
+    }
+}
+
+impl std::iter::Sum for EnergyFlows {
+    fn sum<I: Iterator<Item = EnergyFlows>>(iter: I) -> Self {
+        iter.fold(EnergyFlows::default(), |mut sum, flows| {
+            sum.add(&flows);
+            sum
+        })
+    }
+}
+
+/// How much energy the agents need and how it moves between them, see
+/// [`SystemBuilder::energy_policy`](crate::SystemBuilder::energy_policy).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EnergyPolicy {
+    /// The agents with at most this energy die at the end of every step, 0 by default.
+    pub death_threshold: u32,
+    /// The agents with less energy don't reproduce, unless their actions are chosen by an
+    /// [`ActionSelectionFn`](crate::actions::ActionSelectionFn). 25 by default.
+    pub reproduction_threshold: u32,
+    /// The share of their energy the parents pass on to their children, 0.25 by default.
+    pub reproduction_transfer: f64,
+    pub combat_transfer: CombatTransfer,
+    pub child_split: ChildEnergySplit,
+}
+
+impl Default for EnergyPolicy {
+    fn default() -> Self {
+        EnergyPolicy {
+            death_threshold: 0,
+            reproduction_threshold: 25,
+            reproduction_transfer: 0.25,
+            combat_transfer: CombatTransfer::Fixed(2),
+            child_split: ChildEnergySplit::Even,
+        }
+    }
+}
+
+/// The energy the winner of a combat takes from the loser, never more than the loser has.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CombatTransfer {
+    Fixed(u32),
+    /// The given share of the energy of the loser, rounded down.
+    Proportional(f64),
+}
+
+impl CombatTransfer {
+    pub(crate) fn energy(&self, looser_energy: u32) -> u32 {
+        match *self {
+            CombatTransfer::Fixed(energy) => energy.min(looser_energy),
+            CombatTransfer::Proportional(share) => (share * looser_energy as f64) as u32,
+        }
+    }
+}
+
+/// How the energy passed on by the parents is split between their two children.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ChildEnergySplit {
+    /// Both children get half of the energy of both parents, the second one the odd unit.
+    #[default]
+    Even,
+    /// Every child gets the energy passed on by one of the parents, the first child of the first
+    /// parent.
+    PerParent,
+}
+
+impl ChildEnergySplit {
+    pub(crate) fn split(&self, parent1_energy: u32, parent2_energy: u32) -> [u32; 2] {
+        match self {
+            ChildEnergySplit::Even => {
+                let energy = parent1_energy + parent2_energy;
+                [energy / 2, energy.div_ceil(2)]
+            }
+            ChildEnergySplit::PerParent => [parent1_energy, parent2_energy],
+        }
+    }
+}
+
+/// What happens when the energy isn't conserved.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EnergyValidation {
+    /// The flows are only recorded.
+    Off,
+    /// The broken invariant is printed to the standard error.
+    #[default]
+    Report,
+    /// The run panics on the broken invariant.
+    Panic,
+}
+
+impl EnergyValidation {
+    pub(crate) fn check(&self, expected: i64, actual: i64, context: impl FnOnce() -> String) {
+        if expected == actual {
+            return;
+        }
+        let message = format!(
+            "The energy isn't conserved {}: expected {}, found {}",
+            context(),
+            expected,
+            actual
+        );
+        match self {
+            EnergyValidation::Off => {}
+            EnergyValidation::Report => eprintln!("{}", message),
+            EnergyValidation::Panic => panic!("{}", message),
+        }
+    }
+}
+
+/// The energy flows of an island.
+#[derive(Debug, Clone)]
+pub(crate) struct EnergyLedger {
+    pub(crate) validation: EnergyValidation,
+    /// The flows since the start of the run.
+    pub(crate) total: EnergyFlows,
+    /// The flows of the current step.
+    pub(crate) step: EnergyFlows,
+}
+
+impl EnergyLedger {
+    pub(crate) fn new(validation: EnergyValidation) -> Self {
+        EnergyLedger {
+            validation,
+            total: EnergyFlows::default(),
+            step: EnergyFlows::default(),
+        }
+    }
+
+    /// Adds the flows recorded between the steps, i.e. of the agents leaving the island during
+    /// the migrations, to the total.
+    pub(crate) fn flush(&mut self) {
+        let flows = std::mem::take(&mut self.step);
+        self.total.add(&flows);
+    }
+
+    /// Checks that the energy of the island changed in the step only by the recorded flows.
+    pub(crate) fn close_step(&mut self, island: usize, step: u32, energy_before: u32, energy_after: u32) {
+        let flows = std::mem::take(&mut self.step);
+        let expected = energy_before as i64 + flows.custom_gained as i64
+            - flows.custom_lost as i64
+            - flows.local_search as i64
+            - flows.death as i64
+            - flows.migration as i64;
+        self.validation
+            .check(expected, energy_after as i64, || format!("on island {} in step {}", island, step));
+        self.total.add(&flows);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::energy::*;
+
+    #[test]
+    fn ledger_test() {
+        let mut ledger = EnergyLedger::new(EnergyValidation::Panic);
+        ledger.step.combat = 5;
+        ledger.step.death = 2;
+        ledger.step.custom_gained = 3;
+        ledger.close_step(0, 1, 100, 101);
+        assert_eq!(ledger.total.combat, 5);
+        assert_eq!(ledger.step, EnergyFlows::default());
+
+        ledger.step.local_search = 1;
+        let result = std::panic::catch_unwind(move || ledger.close_step(0, 2, 101, 101));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn transfers_test() {
+        assert_eq!(CombatTransfer::Fixed(5).energy(3), 3);
+        assert_eq!(CombatTransfer::Proportional(0.5).energy(7), 3);
+        assert_eq!(ChildEnergySplit::Even.split(4, 7), [5, 6]);
+        assert_eq!(ChildEnergySplit::PerParent.split(4, 7), [4, 7]);
+    }
+}
@@ -1,16 +1,203 @@
+use crate::constraints::Constraint;
+use crate::genome::Genome;
 use std::f64::consts::PI;
+use std::fmt::{Debug, Formatter};
+use std::sync::Arc;
 
-pub trait FitnessFn<const N: usize> {
-    const DOMAIN: [(f64, f64); N];
-    fn call(args: &[f64; N]) -> f64;
+/// The function optimized by the system. It is shared by all islands, so it has to be `Send` and
+/// `Sync`, but it can hold any state needed to compute the fitness, e.g. a dataset loaded at
+/// runtime.
+///
+/// The domain is asked for once, when the system is built.
+pub trait FitnessFn: Send + Sync {
+    type Genome: Genome;
+
+    fn domain(&self) -> <Self::Genome as Genome>::Domain;
+    fn call(&self, args: &Self::Genome) -> f64;
+
+    /// The objectives of a multi-objective problem, all of them minimized or all of them
+    /// maximized, like the fitness. The systems built with
+    /// [`pareto`](crate::SystemBuilder::pareto) evaluate them instead of `call`, and derive the
+    /// fitness used by the statistics and the termination criteria with `scalarize`.
+    fn objectives(&self, args: &Self::Genome) -> Vec<f64> {
+        vec![self.call(args)]
+    }
+
+    /// The fitness of a multi-objective solution, given its objectives. Their sum by default.
+    fn scalarize(&self, objectives: &[f64]) -> f64 {
+        objectives.iter().sum()
+    }
+
+    /// Evaluates all the offspring of an island step at once, before they fight, e.g. on a
+    /// thread pool or on a GPU. Calls `call` for every genotype by default.
+    fn call_batch(&self, args: &[&Self::Genome]) -> Vec<f64> {
+        args.iter().map(|genes| self.call(genes)).collect()
+    }
+
+    /// Evaluates the objectives of all the offspring of an island step at once, like
+    /// `call_batch`. Calls `objectives` for every genotype by default.
+    fn objectives_batch(&self, args: &[&Self::Genome]) -> Vec<Vec<f64>> {
+        args.iter().map(|genes| self.objectives(genes)).collect()
+    }
+
+    /// The constraints a feasible solution has to satisfy, none by default. How the infeasible
+    /// agents are treated is set with
+    /// [`SystemBuilder::constraint_handling`](crate::SystemBuilder::constraint_handling).
+    ///
+    /// The constraints are evaluated together with the fitness and don't count as separate
+    /// evaluations.
+    fn constraints(&self, _args: &Self::Genome) -> Vec<Constraint> {
+        Vec::new()
+    }
+
+    /// Evaluates the constraints of all the offspring of an island step at once, right after
+    /// their `call_batch` or `objectives_batch`. Calls `constraints` for every genotype by
+    /// default.
+    fn constraints_batch(&self, args: &[&Self::Genome]) -> Vec<Vec<Constraint>> {
+        args.iter().map(|genes| self.constraints(genes)).collect()
+    }
+}
+
+/// Whether the fitness, and the objectives of a multi-objective problem, are minimized or
+/// maximized, see [`SystemBuilder::direction`](crate::SystemBuilder::direction).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Direction {
+    #[default]
+    Minimize,
+    Maximize,
+}
+
+impl Direction {
+    /// The value as the system compares it, the lower being the better. Applied to the compared
+    /// value it gives the original one back.
+    pub fn minimized(self, value: f64) -> f64 {
+        match self {
+            Direction::Minimize => value,
+            Direction::Maximize => -value,
+        }
+    }
 }
 
+impl<F: FitnessFn + ?Sized> FitnessFn for Box<F> {
+    type Genome = F::Genome;
+
+    fn domain(&self) -> <Self::Genome as Genome>::Domain {
+        (**self).domain()
+    }
+
+    fn call(&self, args: &Self::Genome) -> f64 {
+        (**self).call(args)
+    }
+
+    fn objectives(&self, args: &Self::Genome) -> Vec<f64> {
+        (**self).objectives(args)
+    }
+
+    fn scalarize(&self, objectives: &[f64]) -> f64 {
+        (**self).scalarize(objectives)
+    }
+
+    fn call_batch(&self, args: &[&Self::Genome]) -> Vec<f64> {
+        (**self).call_batch(args)
+    }
+
+    fn objectives_batch(&self, args: &[&Self::Genome]) -> Vec<Vec<f64>> {
+        (**self).objectives_batch(args)
+    }
+
+    fn constraints(&self, args: &Self::Genome) -> Vec<Constraint> {
+        (**self).constraints(args)
+    }
+
+    fn constraints_batch(&self, args: &[&Self::Genome]) -> Vec<Vec<Constraint>> {
+        (**self).constraints_batch(args)
+    }
+}
+
+impl<F: FitnessFn + ?Sized> FitnessFn for Arc<F> {
+    type Genome = F::Genome;
+
+    fn domain(&self) -> <Self::Genome as Genome>::Domain {
+        (**self).domain()
+    }
+
+    fn call(&self, args: &Self::Genome) -> f64 {
+        (**self).call(args)
+    }
+
+    fn objectives(&self, args: &Self::Genome) -> Vec<f64> {
+        (**self).objectives(args)
+    }
+
+    fn scalarize(&self, objectives: &[f64]) -> f64 {
+        (**self).scalarize(objectives)
+    }
+
+    fn call_batch(&self, args: &[&Self::Genome]) -> Vec<f64> {
+        (**self).call_batch(args)
+    }
+
+    fn objectives_batch(&self, args: &[&Self::Genome]) -> Vec<Vec<f64>> {
+        (**self).objectives_batch(args)
+    }
+
+    fn constraints(&self, args: &Self::Genome) -> Vec<Constraint> {
+        (**self).constraints(args)
+    }
+
+    fn constraints_batch(&self, args: &[&Self::Genome]) -> Vec<Vec<Constraint>> {
+        (**self).constraints_batch(args)
+    }
+}
+
+/// A fitness function made of a closure and a domain, see [`from_fn`].
+pub struct FnFitness<G: Genome, C> {
+    domain: G::Domain,
+    f: C,
+}
+
+impl<G: Genome, C> Debug for FnFitness<G, C> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FnFitness")
+            .field("domain", &self.domain)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<G: Genome, C> FitnessFn for FnFitness<G, C>
+    where
+        C: Fn(&G) -> f64 + Send + Sync
+{
+    type Genome = G;
+
+    fn domain(&self) -> G::Domain {
+        self.domain.clone()
+    }
+
+    fn call(&self, args: &G) -> f64 {
+        (self.f)(args)
+    }
+}
+
+/// Creates a fitness function from a closure optimized over the given domain.
+pub fn from_fn<G: Genome, C>(domain: G::Domain, f: C) -> FnFitness<G, C>
+    where
+        C: Fn(&G) -> f64 + Send + Sync
+{
+    FnFitness { domain, f }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
 pub struct RastriginFitness<const N: usize> {}
 
-impl<const N: usize> FitnessFn<N> for RastriginFitness<N> {
-    const DOMAIN: [(f64, f64); N] = [(-5.12, 5.12); N];
+impl<const N: usize> FitnessFn for RastriginFitness<N> {
+    type Genome = [f64; N];
 
-    fn call(args: &[f64; N]) -> f64 {
+    fn domain(&self) -> [(f64, f64); N] {
+        [(-5.12, 5.12); N]
+    }
+
+    fn call(&self, args: &[f64; N]) -> f64 {
         let a: f64 = 10.0;
         let mut fx = a * N as f64;
         fx += args
@@ -21,13 +208,74 @@ impl<const N: usize> FitnessFn<N> for RastriginFitness<N> {
     }
 }
 
+/// The amount of zeros in a binary string, so the optimum is the string of ones.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OneMaxFitness<const N: usize> {}
+
+impl<const N: usize> FitnessFn for OneMaxFitness<N> {
+    type Genome = [bool; N];
+
+    fn domain(&self) {}
+
+    fn call(&self, args: &[bool; N]) -> f64 {
+        args.iter().filter(|&&bit| !bit).count() as f64
+    }
+}
+
+/// The 0-1 knapsack problem: the genes tell which items are packed. The fitness of a packing
+/// which fits in the knapsack is its negated value, the fitness of one which doesn't is the
+/// excess weight, so any packing that fits is better than any that doesn't.
+#[derive(Debug, Clone, Default)]
+pub struct KnapsackFitness {
+    pub values: Vec<f64>,
+    pub weights: Vec<f64>,
+    pub capacity: f64,
+}
+
+impl FitnessFn for KnapsackFitness {
+    type Genome = Vec<bool>;
+
+    fn domain(&self) -> usize {
+        assert_eq!(self.values.len(), self.weights.len(), "Every item has to have a value and a weight");
+        self.values.len()
+    }
+
+    fn call(&self, args: &Vec<bool>) -> f64 {
+        let packed = || args.iter().zip(self.values.iter().zip(&self.weights)).filter(|(&bit, _)| bit);
+        let weight = packed().map(|(_, (_, w))| w).sum::<f64>();
+        if weight > self.capacity {
+            return weight - self.capacity;
+        }
+        -packed().map(|(_, (v, _))| v).sum::<f64>()
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::fitness_functions::{RastriginFitness, FitnessFn};
+    use crate::fitness_functions::{from_fn, FitnessFn, KnapsackFitness, RastriginFitness};
 
     #[test]
     fn rastrigin_min_test() {
-        assert_eq!(RastriginFitness::<2>::call(&[0.0, 0.0]), 0.0)
+        assert_eq!(RastriginFitness::<2>::default().call(&[0.0, 0.0]), 0.0)
     }
 
-}
\ No newline at end of file
+    #[test]
+    fn boxed_closure_test() {
+        let offset = 3.0;
+        let f: Box<dyn FitnessFn<Genome = Vec<f64>>> =
+            Box::new(from_fn(vec![(-5.0, 5.0)], move |x: &Vec<f64>| (x[0] - offset).abs()));
+        assert_eq!(f.call(&vec![3.0]), 0.0);
+        assert_eq!(f.domain(), vec![(-5.0, 5.0)]);
+    }
+
+    #[test]
+    fn knapsack_test() {
+        let knapsack = KnapsackFitness {
+            values: vec![3.0, 4.0, 5.0],
+            weights: vec![2.0, 3.0, 4.0],
+            capacity: 5.0,
+        };
+        assert_eq!(knapsack.call(&vec![true, true, false]), -7.0);
+        assert_eq!(knapsack.call(&vec![false, true, true]), 2.0);
+    }
+}
@@ -0,0 +1,425 @@
+use crate::mutation_functions::{BitFlipMutation, CreepMutation, InversionMutation, MutationFn, UniformMutation};
+use crate::recombination_functions::{OrderCrossover, RecombinationFn, SinglePointCrossover};
+use rand::prelude::SliceRandom;
+use rand::{Rng, RngCore};
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt::Debug;
+
+/// The genotype of an agent, i.e. an encoded solution of the optimized problem.
+///
+/// `Domain` describes the space the solutions live in and is supplied by the fitness function
+/// when the system is built, so its size doesn't have to be known at compile time.
+pub trait Genome: Clone + Debug + Send + Sync + 'static {
+    type Domain: Clone + Debug + Send + Sync;
+
+    fn random(domain: &Self::Domain, rng: &mut dyn RngCore) -> Self;
+
+    /// The recombination used unless another one is set in the builder.
+    fn default_recombination() -> Box<dyn RecombinationFn<Self>>;
+
+    /// The mutation used unless another one is set in the builder.
+    fn default_mutation() -> Box<dyn MutationFn<Self>>;
+
+    /// Panics if the domain is malformed, called once when the system is built.
+    fn validate_domain(_domain: &Self::Domain) {}
+
+    /// Used to save the genes in the checkpoints.
+    fn serialize_genes<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error>;
+
+    fn deserialize_genes<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error>;
+}
+
+/// A sequence of genes of a single type, which the generic crossovers can cut and splice.
+pub trait LinearGenome: Genome {
+    type Gene: Copy + Debug + Send + Sync;
+
+    fn genes(&self) -> &[Self::Gene];
+    fn genes_mut(&mut self) -> &mut [Self::Gene];
+}
+
+impl<T: Copy + Debug + Send + Sync, const N: usize> LinearGenome for [T; N]
+    where
+        [T; N]: Genome
+{
+    type Gene = T;
+
+    fn genes(&self) -> &[T] {
+        self
+    }
+
+    fn genes_mut(&mut self) -> &mut [T] {
+        self
+    }
+}
+
+impl<T: Copy + Debug + Send + Sync> LinearGenome for Vec<T>
+    where
+        Vec<T>: Genome
+{
+    type Gene = T;
+
+    fn genes(&self) -> &[T] {
+        self
+    }
+
+    fn genes_mut(&mut self) -> &mut [T] {
+        self
+    }
+}
+
+impl<T: Copy + Debug + Send + Sync> LinearGenome for Box<[T]>
+    where
+        Box<[T]>: Genome
+{
+    type Gene = T;
+
+    fn genes(&self) -> &[T] {
+        self
+    }
+
+    fn genes_mut(&mut self) -> &mut [T] {
+        self
+    }
+}
+
+/// A vector of real numbers, each bounded by the matching `(min, max)` pair of the domain.
+///
+/// Implemented for `[f64; N]`, whose size is known at compile time, and for the heap allocated
+/// `Vec<f64>` and `Box<[f64]>`, whose size is taken from the domain.
+pub trait RealGenome: Genome + AsRef<[f64]> + AsMut<[f64]> {
+    fn bounds(domain: &Self::Domain) -> &[(f64, f64)];
+}
+
+/// A vector of integers, each bounded by the matching inclusive `(min, max)` pair of the domain.
+///
+/// Implemented for `[i64; N]` and `Vec<i64>`.
+pub trait IntegerGenome: LinearGenome<Gene = i64> {
+    fn bounds(domain: &Self::Domain) -> &[(i64, i64)];
+}
+
+/// A binary string, implemented for `[bool; N]`, whose domain is `()`, and for `Vec<bool>`,
+/// whose domain is its length.
+pub trait BinaryGenome: LinearGenome<Gene = bool> {}
+
+fn random_gene((d_min, d_max): (f64, f64), rng: &mut dyn RngCore) -> f64 {
+    d_min + rng.gen::<f64>() * (d_max - d_min)
+}
+
+fn validate_bounds<T: PartialOrd>(bounds: &[(T, T)]) {
+    for (i, (d_min, d_max)) in bounds.iter().enumerate() {
+        if d_min > d_max {
+            panic!("In the domain in argument {}, the first element is larger than the second element, which is not allowed", i)
+        }
+    }
+}
+
+// serde only implements its traits for arrays of up to 32 elements, so the arrays go through
+// a slice and a `Vec`.
+fn deserialize_array<'de, T, const N: usize, D>(deserializer: D) -> Result<[T; N], D::Error>
+    where
+        T: Deserialize<'de>,
+        D: Deserializer<'de>
+{
+    let genes = Vec::<T>::deserialize(deserializer)?;
+    let len = genes.len();
+    genes
+        .try_into()
+        .map_err(|_| D::Error::invalid_length(len, &format!("{} genes", N).as_str()))
+}
+
+impl<const N: usize> Genome for [f64; N] {
+    type Domain = [(f64, f64); N];
+
+    fn random(domain: &Self::Domain, rng: &mut dyn RngCore) -> Self {
+        domain.map(|bounds| random_gene(bounds, rng))
+    }
+
+    fn default_recombination() -> Box<dyn RecombinationFn<Self>> {
+        Box::new(SinglePointCrossover)
+    }
+
+    fn default_mutation() -> Box<dyn MutationFn<Self>> {
+        Box::new(UniformMutation::default())
+    }
+
+    fn validate_domain(domain: &Self::Domain) {
+        validate_bounds(domain)
+    }
+
+    fn serialize_genes<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.as_slice().serialize(serializer)
+    }
+
+    fn deserialize_genes<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_array(deserializer)
+    }
+}
+
+impl<const N: usize> RealGenome for [f64; N] {
+    fn bounds(domain: &Self::Domain) -> &[(f64, f64)] {
+        domain
+    }
+}
+
+impl Genome for Vec<f64> {
+    type Domain = Vec<(f64, f64)>;
+
+    fn random(domain: &Self::Domain, rng: &mut dyn RngCore) -> Self {
+        domain.iter().map(|&bounds| random_gene(bounds, rng)).collect()
+    }
+
+    fn default_recombination() -> Box<dyn RecombinationFn<Self>> {
+        Box::new(SinglePointCrossover)
+    }
+
+    fn default_mutation() -> Box<dyn MutationFn<Self>> {
+        Box::new(UniformMutation::default())
+    }
+
+    fn validate_domain(domain: &Self::Domain) {
+        validate_bounds(domain)
+    }
+
+    fn serialize_genes<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.serialize(serializer)
+    }
+
+    fn deserialize_genes<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Self::deserialize(deserializer)
+    }
+}
+
+impl RealGenome for Vec<f64> {
+    fn bounds(domain: &Self::Domain) -> &[(f64, f64)] {
+        domain
+    }
+}
+
+impl Genome for Box<[f64]> {
+    type Domain = Box<[(f64, f64)]>;
+
+    fn random(domain: &Self::Domain, rng: &mut dyn RngCore) -> Self {
+        domain.iter().map(|&bounds| random_gene(bounds, rng)).collect()
+    }
+
+    fn default_recombination() -> Box<dyn RecombinationFn<Self>> {
+        Box::new(SinglePointCrossover)
+    }
+
+    fn default_mutation() -> Box<dyn MutationFn<Self>> {
+        Box::new(UniformMutation::default())
+    }
+
+    fn validate_domain(domain: &Self::Domain) {
+        validate_bounds(domain)
+    }
+
+    fn serialize_genes<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.serialize(serializer)
+    }
+
+    fn deserialize_genes<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Self::deserialize(deserializer)
+    }
+}
+
+impl RealGenome for Box<[f64]> {
+    fn bounds(domain: &Self::Domain) -> &[(f64, f64)] {
+        domain
+    }
+}
+
+impl<const N: usize> Genome for [i64; N] {
+    type Domain = [(i64, i64); N];
+
+    fn random(domain: &Self::Domain, rng: &mut dyn RngCore) -> Self {
+        domain.map(|(d_min, d_max)| rng.gen_range(d_min..=d_max))
+    }
+
+    fn default_recombination() -> Box<dyn RecombinationFn<Self>> {
+        Box::new(SinglePointCrossover)
+    }
+
+    fn default_mutation() -> Box<dyn MutationFn<Self>> {
+        Box::new(CreepMutation::default())
+    }
+
+    fn validate_domain(domain: &Self::Domain) {
+        validate_bounds(domain)
+    }
+
+    fn serialize_genes<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.as_slice().serialize(serializer)
+    }
+
+    fn deserialize_genes<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_array(deserializer)
+    }
+}
+
+impl<const N: usize> IntegerGenome for [i64; N] {
+    fn bounds(domain: &Self::Domain) -> &[(i64, i64)] {
+        domain
+    }
+}
+
+impl Genome for Vec<i64> {
+    type Domain = Vec<(i64, i64)>;
+
+    fn random(domain: &Self::Domain, rng: &mut dyn RngCore) -> Self {
+        domain.iter().map(|&(d_min, d_max)| rng.gen_range(d_min..=d_max)).collect()
+    }
+
+    fn default_recombination() -> Box<dyn RecombinationFn<Self>> {
+        Box::new(SinglePointCrossover)
+    }
+
+    fn default_mutation() -> Box<dyn MutationFn<Self>> {
+        Box::new(CreepMutation::default())
+    }
+
+    fn validate_domain(domain: &Self::Domain) {
+        validate_bounds(domain)
+    }
+
+    fn serialize_genes<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.serialize(serializer)
+    }
+
+    fn deserialize_genes<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Self::deserialize(deserializer)
+    }
+}
+
+impl IntegerGenome for Vec<i64> {
+    fn bounds(domain: &Self::Domain) -> &[(i64, i64)] {
+        domain
+    }
+}
+
+impl<const N: usize> Genome for [bool; N] {
+    type Domain = ();
+
+    fn random(_domain: &(), rng: &mut dyn RngCore) -> Self {
+        [(); N].map(|_| rng.gen())
+    }
+
+    fn default_recombination() -> Box<dyn RecombinationFn<Self>> {
+        Box::new(SinglePointCrossover)
+    }
+
+    fn default_mutation() -> Box<dyn MutationFn<Self>> {
+        Box::new(BitFlipMutation::default())
+    }
+
+    fn serialize_genes<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.as_slice().serialize(serializer)
+    }
+
+    fn deserialize_genes<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_array(deserializer)
+    }
+}
+
+impl<const N: usize> BinaryGenome for [bool; N] {}
+
+impl Genome for Vec<bool> {
+    type Domain = usize;
+
+    fn random(domain: &usize, rng: &mut dyn RngCore) -> Self {
+        (0..*domain).map(|_| rng.gen()).collect()
+    }
+
+    fn default_recombination() -> Box<dyn RecombinationFn<Self>> {
+        Box::new(SinglePointCrossover)
+    }
+
+    fn default_mutation() -> Box<dyn MutationFn<Self>> {
+        Box::new(BitFlipMutation::default())
+    }
+
+    fn serialize_genes<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.serialize(serializer)
+    }
+
+    fn deserialize_genes<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Self::deserialize(deserializer)
+    }
+}
+
+impl BinaryGenome for Vec<bool> {}
+
+/// An ordering of the numbers `0..n`, e.g. the order in which the cities are visited. The domain
+/// is `n`.
+///
+/// It isn't a [`LinearGenome`], since cutting and splicing two permutations doesn't give a
+/// permutation, it has its own operators instead.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Permutation(pub Vec<usize>);
+
+impl Permutation {
+    /// Whether every number in `0..len` appears exactly once.
+    pub fn is_valid(&self) -> bool {
+        let mut seen = vec![false; self.0.len()];
+        self.0
+            .iter()
+            .all(|&i| i < seen.len() && !std::mem::replace(&mut seen[i], true))
+    }
+}
+
+impl AsRef<[usize]> for Permutation {
+    fn as_ref(&self) -> &[usize] {
+        &self.0
+    }
+}
+
+impl Genome for Permutation {
+    type Domain = usize;
+
+    fn random(domain: &usize, rng: &mut dyn RngCore) -> Self {
+        let mut order: Vec<_> = (0..*domain).collect();
+        order.shuffle(rng);
+        Permutation(order)
+    }
+
+    fn default_recombination() -> Box<dyn RecombinationFn<Self>> {
+        Box::new(OrderCrossover)
+    }
+
+    fn default_mutation() -> Box<dyn MutationFn<Self>> {
+        Box::new(InversionMutation::default())
+    }
+
+    fn serialize_genes<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.serialize(serializer)
+    }
+
+    fn deserialize_genes<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let permutation = Self::deserialize(deserializer)?;
+        if !permutation.is_valid() {
+            return Err(D::Error::custom("The genes aren't a permutation"));
+        }
+        Ok(permutation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::genome::Genome;
+    use rand::thread_rng;
+
+    #[test]
+    fn random_genome_in_domain_test() {
+        let domain = vec![(1.0, 2.0), (-3.0, -2.0), (0.0, 0.0)];
+        let genes = Vec::<f64>::random(&domain, &mut thread_rng());
+        assert_eq!(genes.len(), 3);
+        assert!(genes.iter().zip(&domain).all(|(g, (d_min, d_max))| d_min <= g && g <= d_max));
+
+        let domain = [(-1, 1), (5, 5)];
+        for _ in 0..20 {
+            let genes = <[i64; 2]>::random(&domain, &mut thread_rng());
+            assert!((-1..=1).contains(&genes[0]) && genes[1] == 5);
+        }
+    }
+}
@@ -0,0 +1,152 @@
+use std::collections::BTreeSet;
+
+/// The cells next to a cell of a [`Lattice`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Neighbourhood {
+    /// The 4 cells above, below, to the left and to the right.
+    #[default]
+    VonNeumann,
+    /// The 8 cells around, including the diagonal ones.
+    Moore,
+}
+
+/// A `width` x `height` grid with wrapping edges, on which the agents of a spatially structured
+/// island live, see [`SystemBuilder::lattice`](crate::SystemBuilder::lattice). The cells are
+/// numbered row by row.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Lattice {
+    pub width: usize,
+    pub height: usize,
+    pub neighbourhood: Neighbourhood,
+}
+
+impl Lattice {
+    pub fn cells(&self) -> usize {
+        self.width * self.height
+    }
+
+    /// The neighbours of the cell, without the cell itself and the duplicates, which appear on
+    /// the small lattices.
+    pub fn neighbours(&self, cell: usize) -> Vec<usize> {
+        let Lattice { width, height, .. } = *self;
+        let (x, y) = (cell % width, cell / width);
+        let offsets: &[(usize, usize)] = match self.neighbourhood {
+            Neighbourhood::VonNeumann => &[(width - 1, 0), (1, 0), (0, height - 1), (0, 1)],
+            Neighbourhood::Moore => &[
+                (width - 1, height - 1),
+                (0, height - 1),
+                (1, height - 1),
+                (width - 1, 0),
+                (1, 0),
+                (width - 1, 1),
+                (0, 1),
+                (1, 1),
+            ],
+        };
+        let neighbours: BTreeSet<_> = offsets
+            .iter()
+            .map(|(dx, dy)| (y + dy) % height * width + (x + dx) % width)
+            .filter(|&neighbour| neighbour != cell)
+            .collect();
+        neighbours.into_iter().collect()
+    }
+
+}
+
+/// A [`Lattice`] together with the neighbours of every cell, computed once, as the islands look
+/// them up for every combat and reproduction.
+#[derive(Debug, Clone)]
+pub(crate) struct Grid {
+    lattice: Lattice,
+    neighbours: Vec<Vec<usize>>,
+}
+
+impl Grid {
+    pub(crate) fn new(lattice: Lattice) -> Self {
+        Grid {
+            lattice,
+            neighbours: (0..lattice.cells()).map(|cell| lattice.neighbours(cell)).collect(),
+        }
+    }
+
+    pub(crate) fn cells(&self) -> usize {
+        self.lattice.cells()
+    }
+
+    /// The neighbours of the cell, sorted, see [`Lattice::neighbours`].
+    pub(crate) fn neighbours(&self, cell: usize) -> &[usize] {
+        &self.neighbours[cell]
+    }
+
+    pub(crate) fn are_neighbours(&self, cell1: usize, cell2: usize) -> bool {
+        self.neighbours[cell1].binary_search(&cell2).is_ok()
+    }
+
+    /// The occupancy, the average absolute difference of the fitness between the neighbours and
+    /// the spatial autocorrelation of the fitness (Moran's I), given the fitness in every cell.
+    /// The last two are `None` if no neighbouring cells are occupied, the autocorrelation also
+    /// if all the fitness values are the same.
+    pub(crate) fn diversity(&self, fitness: &[Option<f64>]) -> (f64, Option<f64>, Option<f64>) {
+        let occupied: Vec<_> = fitness.iter().flatten().copied().collect();
+        let occupancy = occupied.len() as f64 / self.cells() as f64;
+        let mean = occupied.iter().sum::<f64>() / occupied.len() as f64;
+        let variance = occupied.iter().map(|f| (f - mean).powi(2)).sum::<f64>();
+
+        let (mut pairs, mut difference, mut covariance) = (0, 0.0, 0.0);
+        for (cell, f1) in fitness.iter().enumerate() {
+            let Some(f1) = f1 else { continue };
+            for f2 in self.neighbours(cell).iter().filter_map(|&neighbour| fitness[neighbour]) {
+                pairs += 1;
+                difference += (f1 - f2).abs();
+                covariance += (f1 - mean) * (f2 - mean);
+            }
+        }
+        if pairs == 0 {
+            return (occupancy, None, None);
+        }
+        let autocorrelation = (variance > 0.0)
+            .then(|| occupied.len() as f64 / pairs as f64 * covariance / variance);
+        (occupancy, Some(difference / pairs as f64), autocorrelation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lattice::*;
+
+    #[test]
+    fn neighbours_test() {
+        let lattice = Lattice {
+            width: 4,
+            height: 3,
+            neighbourhood: Neighbourhood::VonNeumann,
+        };
+        assert_eq!(lattice.neighbours(0), vec![1, 3, 4, 8]);
+        let moore = Lattice {
+            neighbourhood: Neighbourhood::Moore,
+            ..lattice
+        };
+        assert_eq!(moore.neighbours(5), vec![0, 1, 2, 4, 6, 8, 9, 10]);
+        assert_eq!(Lattice { width: 2, height: 1, ..moore }.neighbours(0), vec![1]);
+
+        let grid = Grid::new(moore);
+        assert!(grid.are_neighbours(5, 10) && grid.are_neighbours(10, 5));
+        assert!(!grid.are_neighbours(5, 5) && !grid.are_neighbours(5, 7));
+    }
+
+    #[test]
+    fn diversity_test() {
+        let lattice = Grid::new(Lattice {
+            width: 2,
+            height: 2,
+            neighbourhood: Neighbourhood::VonNeumann,
+        });
+        // A checkerboard, every neighbour is different.
+        let (occupancy, difference, autocorrelation) =
+            lattice.diversity(&[Some(0.0), Some(1.0), Some(1.0), Some(0.0)]);
+        assert_eq!(occupancy, 1.0);
+        assert_eq!(difference, Some(1.0));
+        assert_eq!(autocorrelation, Some(-1.0));
+        assert_eq!(lattice.diversity(&[Some(0.0), None, None, Some(1.0)]), (0.5, None, None));
+    }
+}
@@ -1,336 +1,977 @@
 use rand::prelude::SliceRandom;
-use rand::{random, seq::IteratorRandom, thread_rng, Rng};
-use std::collections::HashMap;
-use std::fs::File;
+use rand::{seq::index, seq::IteratorRandom, Rng, RngCore, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use rayon::prelude::*;
+use rayon::{ThreadPool, ThreadPoolBuilder};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{self, File};
 use std::hash::Hash;
-use std::io::Write;
+use std::io;
+use std::io::{BufReader, BufWriter};
 use std::marker::PhantomData;
-use std::time::Instant;
-use std::fs;
-use fitness_functions::FitnessFn;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use fitness_functions::{Direction, FitnessFn};
+use genome::Genome;
 use conf_functions::*;
+use recombination_functions::*;
+use mutation_functions::*;
+use migration_topologies::*;
+use termination::*;
+use log_sinks::*;
+use checkpoint::*;
+use pareto::*;
+use constraints::*;
+use local_search::*;
+use actions::*;
+use partner_selection::*;
+use energy::*;
+use lattice::*;
 
 pub mod fitness_functions;
+pub mod genome;
 pub mod conf_functions;
-
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub mod recombination_functions;
+pub mod mutation_functions;
+pub mod migration_topologies;
+pub mod termination;
+pub mod log_sinks;
+pub mod pareto;
+pub mod tsp;
+pub mod constraints;
+pub mod local_search;
+pub mod actions;
+pub mod partner_selection;
+pub mod energy;
+pub mod lattice;
+mod checkpoint;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 struct AgentId(usize, usize);
 
-#[derive(Debug)]
-struct Agent<const N: usize, F: FitnessFn<N>> {
-    genes: [f64; N],
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(bound = "")]
+struct Agent<G: Genome> {
+    #[serde(serialize_with = "G::serialize_genes", deserialize_with = "G::deserialize_genes")]
+    genes: G,
     energy: u32,
     id: AgentId,
+    /// Minimized, i.e. negated if the system maximizes, like all the fitness values compared
+    /// inside the system. See [`Direction::minimized`].
+    #[serde(with = "checkpoint::f64_bits")]
     fitness: f64,
-    f_phantom: PhantomData<F>,
+    /// Only evaluated if the problem is multi-objective. Minimized like the fitness.
+    #[serde(with = "checkpoint::f64_vec_bits")]
+    objectives: Vec<f64>,
+    /// The violation of the constraints, zero if the agent is feasible.
+    #[serde(with = "checkpoint::f64_bits")]
+    violation: f64,
+    /// The amount of the combats won.
+    prestige: u32,
+    /// The step in which the agent was born.
+    born: u32,
+    /// The cell of the lattice the agent lives in, if its island has one.
+    #[serde(default)]
+    cell: Option<usize>,
+    /// The evaluation of the genes found by a Baldwinian local search, which the agent fights
+    /// with instead of the evaluation of its own genes.
+    #[serde(default)]
+    learned: Option<Evaluation>,
 }
 
-impl<const N: usize, F: FitnessFn<N>> Clone for Agent<N, F> {
-    fn clone(&self) -> Self {
-        Agent {
-            genes: self.genes.clone(),
-            energy: self.energy,
-            id: self.id,
-            fitness: self.fitness,
-            f_phantom: PhantomData::default(),
-        }
-    }
+/// An agent which isn't evaluated yet.
+struct Newborn<G> {
+    genes: G,
+    energy: u32,
+    id: AgentId,
 }
 
-
 pub struct ReproductionChance(pub f64);
 
-impl<const N: usize, F: FitnessFn<N>> Agent<N, F> {
-    fn rand_agent(starting_energy: u32, id: AgentId) -> Agent<N, F> {
-        let mut genes = [0.0; N];
-        for (gene, (d_min, d_max)) in genes.iter_mut().zip(F::DOMAIN) {
-            let domain_len = d_max - d_min;
-            *gene = random::<f64>() * domain_len - domain_len / 2.0;
-        }
+impl<G: Genome> Agent<G> {
+    /// Evaluates the newborns with [`Problem::evaluate`]. Returns the agents and the amount of
+    /// the evaluations made.
+    fn evaluate<F: FitnessFn<Genome = G>>(newborns: Vec<Newborn<G>>, problem: &Problem<F>) -> (Vec<Agent<G>>, u64) {
+        let genes: Vec<_> = newborns.iter().map(|newborn| &newborn.genes).collect();
+        let evaluations = problem.evaluate(&genes);
+        let agents = newborns
+            .into_iter()
+            .zip(evaluations)
+            .map(|(newborn, evaluation)| Agent::evaluated(newborn, evaluation))
+            .collect::<Vec<_>>();
+        let evaluations = agents.len() as u64;
+        (agents, evaluations)
+    }
+
+    fn evaluated(Newborn { genes, energy, id }: Newborn<G>, evaluation: Evaluation) -> Agent<G> {
         Agent {
+            fitness: evaluation.fitness,
+            objectives: evaluation.objectives,
+            violation: evaluation.violation,
+            prestige: 0,
+            born: 0,
+            cell: None,
+            learned: None,
             genes,
-            energy: starting_energy,
+            energy,
             id,
-            fitness: F::call(&genes),
-            f_phantom: PhantomData::default(),
         }
     }
 
-    fn reproduce(
-        &mut self,
-        other: &mut Agent<N, F>,
-        energy_passed_percent: f64,
-        ch1_id: AgentId,
-        ch2_id: AgentId,
-    ) -> (Agent<N, F>, Agent<N, F>) {
-        let par1_en = (energy_passed_percent * self.energy as f64) as u32;
-        let par2_en = (energy_passed_percent * other.energy as f64) as u32;
-        self.energy -= par1_en;
-        other.energy -= par2_en;
-
-        let mut ch1 = Agent {
-            energy: (par1_en + par2_en) / 2,
-            id: ch1_id,
-            genes: [0.0; N],
-            fitness: 0.0,
-            f_phantom: PhantomData::default(),
-        };
-        let mut ch2 = Agent {
-            energy: (par1_en + par2_en + 1) / 2,
-            id: ch2_id,
-            genes: [0.0; N],
-            fitness: 0.0,
-            f_phantom: PhantomData::default(),
-        };
-
-        let cut_point = thread_rng().gen_range(0..self.genes.len());
-        for i in 0..cut_point {
-            ch1.genes[i] = self.genes[i];
-            ch2.genes[i] = other.genes[i];
+    fn rand_newborn<F: FitnessFn<Genome = G>>(
+        starting_energy: u32,
+        id: AgentId,
+        problem: &Problem<F>,
+        operators: &GeneticOperators<G>,
+        rng: &mut dyn RngCore,
+    ) -> Newborn<G> {
+        let mut genes = G::random(&problem.domain, rng);
+        if let Some(repair) = &operators.repair {
+            repair.call(&mut genes, &problem.domain, rng);
         }
-        for i in cut_point..self.genes.len() {
-            ch1.genes[i] = other.genes[i];
-            ch1.genes[i] = self.genes[i];
+        Newborn {
+            genes,
+            energy: starting_energy,
+            id,
         }
+    }
 
-        ch1.mutate();
-        ch2.mutate();
+    /// The fitness and the violation the agent fights with, the learned ones if it has any.
+    fn fighting_fitness(&self) -> (f64, f64) {
+        match &self.learned {
+            Some(learned) => (learned.fitness, learned.violation),
+            None => (self.fitness, self.violation),
+        }
+    }
 
-        ch1.fitness = F::call(&ch1.genes);
-        ch2.fitness = F::call(&ch2.genes);
+    /// The objectives the agent fights with, the learned ones if it has any.
+    fn objectives(&self) -> &[f64] {
+        let (fitness, objectives) = match &self.learned {
+            Some(learned) => (&learned.fitness, &learned.objectives),
+            None => (&self.fitness, &self.objectives),
+        };
+        if objectives.is_empty() {
+            std::slice::from_ref(fitness)
+        } else {
+            objectives
+        }
+    }
 
+    fn combatant(&self, crowding_distance: f64, handling: &ConstraintHandling, step: u32) -> Combatant<'_> {
+        let (fitness, violation) = self.fighting_fitness();
+        let (fitness, violation) = handling.assess(fitness, violation, step);
+        Combatant {
+            fitness,
+            objectives: self.objectives(),
+            crowding_distance,
+            violation,
+        }
+    }
 
-        (ch1, ch2)
+    /// Orders the agents from the best one, as the combats compare the evaluations of their genes
+    /// at the given step.
+    fn compare(&self, other: &Agent<G>, handling: &ConstraintHandling, step: u32) -> Ordering {
+        handling.compare((self.fitness, self.violation), (other.fitness, other.violation), step)
     }
 
-    fn mutate(&mut self) {
-        let mut rng = thread_rng();
-        let gene_mut_chance = 1.0;
-        for i in 0..N {
-            let mutation_range = (F::DOMAIN[i].1 - F::DOMAIN[i].0) / 20.0;
-            if rng.gen::<f64>() < gene_mut_chance {
-                let mutation_value = rng.gen::<f64>() * mutation_range;
-                if rng.gen() {
-                    self.genes[i] = (self.genes[i] + mutation_value).min(F::DOMAIN[i].1);
-                } else {
-                    self.genes[i] = (self.genes[i] - mutation_value).max(F::DOMAIN[i].0);
-                }
-            }
+    fn reproduce<F: FitnessFn<Genome = G>>(
+        &mut self,
+        other: &mut Agent<G>,
+        policy: &EnergyPolicy,
+        problem: &Problem<F>,
+        operators: &GeneticOperators<G>,
+        (ch1_id, ch2_id): (AgentId, AgentId),
+        rng: &mut dyn RngCore,
+    ) -> [Newborn<G>; 2] {
+        let par1_en = (policy.reproduction_transfer * self.energy as f64) as u32;
+        let par2_en = (policy.reproduction_transfer * other.energy as f64) as u32;
+        self.energy -= par1_en;
+        other.energy -= par2_en;
+
+        let (mut genes1, mut genes2) = operators.recombination.call(&self.genes, &other.genes, &problem.domain, rng);
+        operators.mutation.call(&mut genes1, &problem.domain, rng);
+        operators.mutation.call(&mut genes2, &problem.domain, rng);
+        if let Some(repair) = &operators.repair {
+            repair.call(&mut genes1, &problem.domain, rng);
+            repair.call(&mut genes2, &problem.domain, rng);
         }
+
+        let [ch1_en, ch2_en] = policy.child_split.split(par1_en, par2_en);
+        [
+            Newborn {
+                genes: genes1,
+                energy: ch1_en,
+                id: ch1_id,
+            },
+            Newborn {
+                genes: genes2,
+                energy: ch2_en,
+                id: ch2_id,
+            },
+        ]
     }
 
-    fn combat(&mut self, other: &mut Agent<N, F>, energy: u32, win_chance_fn: fn(f64, f64) -> f64) {
+    fn combat(
+        &mut self,
+        other: &mut Agent<G>,
+        transfer: CombatTransfer,
+        win_chance: f64,
+        rng: &mut dyn RngCore,
+    ) -> u32 {
         let (winner, looser) =
-            if thread_rng().gen::<f64>() < win_chance_fn(self.fitness, other.fitness) {
+            if rng.gen::<f64>() < win_chance {
                 (self, other)
             } else {
                 (other, self)
             };
-        let energy = energy.min(looser.energy);
+        let energy = transfer.energy(looser.energy);
         looser.energy -= energy;
         winner.energy += energy;
+        winner.prestige += 1;
+        energy
+    }
+
+    fn state(&self, step: u32) -> AgentState {
+        AgentState {
+            energy: self.energy,
+            fitness: self.fitness,
+            age: step - self.born,
+            prestige: self.prestige,
+        }
     }
 
-    fn pick_action(&self, reproduction_chance: ReproductionChance) -> Action {
+    fn pick_action(
+        &self,
+        reproduction_chance: ReproductionChance,
+        migration_mode: MigrationMode,
+        local_search: Option<&LocalSearchConf<G>>,
+        rng: &mut dyn RngCore,
+    ) -> Action {
         let ReproductionChance(reproduction_chance) = reproduction_chance;
-        if thread_rng().gen::<f64>() < reproduction_chance {
+        if rng.gen::<f64>() < reproduction_chance {
             Action::Reproduce
+        } else if matches!(
+            migration_mode,
+            MigrationMode::AgentInitiated { energy_threshold, chance }
+                if self.energy >= energy_threshold && rng.gen::<f64>() < chance
+        ) {
+            Action::Migrate
+        } else if local_search.is_some_and(|ls| self.energy > ls.energy && rng.gen::<f64>() < ls.chance) {
+            Action::LocalSearch
         } else {
             Action::Combat
         }
     }
 }
 
-impl<const N: usize, F: FitnessFn<N>> PartialEq for Agent<N, F> {
+impl<G: Genome> PartialEq for Agent<G> {
     fn eq(&self, other: &Self) -> bool {
         self.id.0 == other.id.0 && self.id.1 == other.id.1
     }
 }
 
-impl<const N: usize, F: FitnessFn<N>> Eq for Agent<N, F> {}
+impl<G: Genome> Eq for Agent<G> {}
+
+/// The optimized function together with its domain, which is only computed once.
+#[derive(Debug)]
+struct Problem<F: FitnessFn> {
+    fitness: F,
+    domain: <F::Genome as Genome>::Domain,
+    /// The reference point is minimized like the objectives.
+    pareto: Option<ParetoConf>,
+    direction: Direction,
+    constraint_handling: ConstraintHandling,
+    equality_tolerance: f64,
+}
+
+/// What the evaluation of a genotype tells, minimized like the values kept by an [`Agent`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Evaluation {
+    #[serde(with = "checkpoint::f64_bits")]
+    fitness: f64,
+    /// Empty unless the problem is multi-objective.
+    #[serde(with = "checkpoint::f64_vec_bits")]
+    objectives: Vec<f64>,
+    #[serde(with = "checkpoint::f64_bits")]
+    violation: f64,
+}
+
+impl<F: FitnessFn> Problem<F> {
+    /// Evaluates the genotypes with a single [`FitnessFn::call_batch`], and their constraints with
+    /// a single [`FitnessFn::constraints_batch`]. A multi-objective problem evaluates the
+    /// objectives with [`FitnessFn::objectives_batch`] instead, and derives the fitness from them
+    /// with [`FitnessFn::scalarize`], so every genotype is evaluated once either way.
+    fn evaluate(&self, genes: &[&F::Genome]) -> Vec<Evaluation> {
+        let (fitness, objectives): (Vec<_>, Vec<_>) = match &self.pareto {
+            Some(pareto) => {
+                let objectives = self.fitness.objectives_batch(genes);
+                assert_eq!(
+                    objectives.len(),
+                    genes.len(),
+                    "The batch evaluation has to return the objectives of every genotype"
+                );
+                objectives
+                    .into_iter()
+                    .map(|objectives| {
+                        assert_eq!(
+                            objectives.len(),
+                            pareto.reference_point.len(),
+                            "The reference point has to have a value for every objective"
+                        );
+                        let fitness = self.fitness.scalarize(&objectives);
+                        (fitness, objectives.into_iter().map(|o| self.direction.minimized(o)).collect())
+                    })
+                    .unzip()
+            }
+            None => {
+                let fitness = self.fitness.call_batch(genes);
+                assert_eq!(
+                    fitness.len(),
+                    genes.len(),
+                    "The batch evaluation has to return the fitness of every genotype"
+                );
+                let objectives = vec![Vec::new(); fitness.len()];
+                (fitness, objectives)
+            }
+        };
+        let constraints = self.fitness.constraints_batch(genes);
+        assert_eq!(
+            constraints.len(),
+            genes.len(),
+            "The batch evaluation has to return the constraints of every genotype"
+        );
+
+        fitness
+            .into_iter()
+            .zip(objectives)
+            .zip(constraints)
+            .map(|((fitness, objectives), constraints)| Evaluation {
+                fitness: self.direction.minimized(fitness),
+                objectives,
+                violation: violation(&constraints, self.equality_tolerance),
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug)]
+struct GeneticOperators<G: Genome> {
+    recombination: Box<dyn RecombinationFn<G>>,
+    mutation: Box<dyn MutationFn<G>>,
+    repair: Option<Box<dyn RepairFn<G>>>,
+    local_search: Option<LocalSearchConf<G>>,
+    action_selection: Option<Box<dyn ActionSelectionFn>>,
+    partner_selection: Option<Box<dyn PartnerSelectionFn<G>>>,
+}
 
-enum Action {
-    Combat,
-    Reproduce,
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum IslandRole {
+    /// Its agents reproduce, fight and migrate.
+    Regular,
+    /// Its agents came from the regular islands with enough prestige, they only fight.
+    Elite,
 }
 
 #[derive(Debug)]
-struct Island<const N: usize, F, CF, RF>
+struct Island<F, CF, RF>
     where
-        F: FitnessFn<N>,
+        F: FitnessFn,
         CF: CombatWinChanceFn,
         RF: ReproductionChanceFn
 {
-    _id: usize,
-    agents: HashMap<AgentId, Agent<N, F>>,
-    migration_queue: Vec<Agent<N, F>>,
+    id: usize,
+    role: IslandRole,
+    agents: BTreeMap<AgentId, Agent<F::Genome>>,
+    migration_queue: Vec<Agent<F::Genome>>,
     last_agent_id: usize,
-    historical_best: Agent<N, F>,
+    /// `None` only on the elite island, where no agents are born.
+    historical_best: Option<Agent<F::Genome>>,
+    best_feasible: Option<Agent<F::Genome>>,
+    /// The non-dominated agents, only kept if the problem is multi-objective.
+    archive: Option<ParetoArchive<F::Genome>>,
+    evaluations: u64,
+    /// The part of the evaluations made by the local search.
+    local_search_evaluations: u64,
+    /// Only kept if the system was built with [`SystemBuilder::energy_ledger`].
+    ledger: Option<EnergyLedger>,
+    /// Only set on the regular islands of a system built with [`SystemBuilder::lattice`].
+    lattice: Option<Grid>,
+    rng: ChaCha8Rng,
     f_phantom: PhantomData<F>,
     cf_phantom: PhantomData<CF>,
     rf_phantom: PhantomData<RF>,
 }
 
-impl<const N: usize, F, CF, RF> Island<N, F, CF, RF>
+impl<F, CF, RF> Island<F, CF, RF>
     where
-        F: FitnessFn<N>,
+        F: FitnessFn,
         CF: CombatWinChanceFn,
         RF: ReproductionChanceFn
 {
-    fn new(agents_amount: usize, agent_energy: u32, id: usize) -> Island<N, F, CF, RF> {
-        let agents: HashMap<AgentId, Agent<N, F>> = (0..agents_amount)
-            .map(|a_id| {
-                (
-                    AgentId(id, a_id),
-                    Agent::rand_agent(agent_energy, AgentId(id, a_id)),
-                )
-            })
+    fn new(
+        agents_amount: usize,
+        agent_energy: u32,
+        id: usize,
+        problem: &Problem<F>,
+        operators: &GeneticOperators<F::Genome>,
+        mut rng: ChaCha8Rng,
+    ) -> Island<F, CF, RF> {
+        let newborns = (0..agents_amount)
+            .map(|a_id| Agent::rand_newborn(agent_energy, AgentId(id, a_id), problem, operators, &mut rng))
             .collect();
+        let (agents, evaluations) = Agent::evaluate(newborns, problem);
+        let agents: BTreeMap<AgentId, Agent<F::Genome>> = agents.into_iter().map(|agent| (agent.id, agent)).collect();
+
+        let mut island = Self::empty(id, IslandRole::Regular, problem, rng);
+        island.last_agent_id = agents_amount - 1;
+        island.evaluations = evaluations;
+        for agent in agents.values() {
+            island.record(agent, problem, 0);
+        }
+        island.agents = agents;
+        island
+    }
 
-        let historical_best = agents.get(&AgentId(id, 0)).unwrap().clone();
+    fn empty(id: usize, role: IslandRole, problem: &Problem<F>, rng: ChaCha8Rng) -> Island<F, CF, RF> {
         Island {
-            _id: id,
-            agents,
+            id,
+            role,
+            agents: BTreeMap::new(),
             migration_queue: Vec::new(),
-            last_agent_id: agents_amount - 1,
-            historical_best,
-            f_phantom: PhantomData::default(),
-            cf_phantom: PhantomData::default(),
-            rf_phantom: PhantomData::default(),
+            last_agent_id: 0,
+            historical_best: None,
+            best_feasible: None,
+            archive: problem.pareto.as_ref().map(|pareto| ParetoArchive::new(pareto.archive_capacity)),
+            evaluations: 0,
+            local_search_evaluations: 0,
+            ledger: None,
+            lattice: None,
+            rng,
+            f_phantom: PhantomData,
+            cf_phantom: PhantomData,
+            rf_phantom: PhantomData,
+        }
+    }
+
+    /// Keeps track of the best solutions found, given a newly evaluated agent.
+    fn record(&mut self, agent: &Agent<F::Genome>, problem: &Problem<F>, step: u32) {
+        if self
+            .historical_best
+            .as_ref()
+            .is_none_or(|best| agent.compare(best, &problem.constraint_handling, step).is_lt())
+        {
+            self.historical_best = Some(agent.clone());
+        }
+        if agent.violation == 0.0 && self.best_feasible.as_ref().is_none_or(|best| agent.fitness < best.fitness) {
+            self.best_feasible = Some(agent.clone());
+        }
+        if let Some(archive) = &mut self.archive {
+            archive.insert(&agent.genes, &agent.objectives);
+        }
+    }
+
+    fn checkpoint(&self) -> IslandCheckpoint<F::Genome> {
+        IslandCheckpoint {
+            agents: self.agents.values().cloned().collect(),
+            migration_queue: self.migration_queue.clone(),
+            last_agent_id: self.last_agent_id,
+            historical_best: self.historical_best.clone(),
+            best_feasible: self.best_feasible.clone(),
+            archive: self.archive.clone(),
+            evaluations: self.evaluations,
+            local_search_evaluations: self.local_search_evaluations,
+            energy_flows: self.ledger.as_ref().map(|ledger| ledger.total),
+            rng: self.rng.clone(),
+        }
+    }
+
+    fn restore(&mut self, checkpoint: IslandCheckpoint<F::Genome>) {
+        self.agents = checkpoint.agents.into_iter().map(|a| (a.id, a)).collect();
+        self.migration_queue = checkpoint.migration_queue;
+        self.last_agent_id = checkpoint.last_agent_id;
+        self.historical_best = checkpoint.historical_best;
+        self.best_feasible = checkpoint.best_feasible;
+        self.archive = checkpoint.archive;
+        self.evaluations = checkpoint.evaluations;
+        self.local_search_evaluations = checkpoint.local_search_evaluations;
+        if let Some((ledger, flows)) = self.ledger.as_mut().zip(checkpoint.energy_flows) {
+            ledger.total = flows;
+        }
+        self.rng = checkpoint.rng;
+    }
+
+    /// Places the agents in random distinct cells of the lattice.
+    fn scatter_agents(&mut self) {
+        let Some(cells) = self.lattice.as_ref().map(Grid::cells) else { return };
+        let cells = index::sample(&mut self.rng, cells, self.agents.len());
+        for (agent, cell) in self.agents.values_mut().zip(cells) {
+            agent.cell = Some(cell);
         }
     }
 
+    fn occupied_cells(&self) -> BTreeSet<usize> {
+        self.agents.values().filter_map(|a| a.cell).collect()
+    }
+
+    /// The indexes of the agents in `agents` living next to the agent.
+    fn neighbour_indexes(&self, lattice: &Grid, id: &AgentId, agents: &[AgentId]) -> Vec<usize> {
+        let cell = self.agents[id].cell.unwrap();
+        agents
+            .iter()
+            .enumerate()
+            .filter(|(_, other)| lattice.are_neighbours(cell, self.agents[other].cell.unwrap()))
+            .map(|(i, _)| i)
+            .collect()
+    }
+
     fn new_agent_id(&mut self) -> usize {
         self.last_agent_id += 1;
         self.last_agent_id
     }
 
-    fn get_pair_mut(&mut self, a1_id: &AgentId, a2_id: &AgentId) -> (&mut Agent<N, F>, &mut Agent<N, F>) {
+    fn get_pair_mut<'a>(
+        agents: &'a mut BTreeMap<AgentId, Agent<F::Genome>>,
+        a1_id: &AgentId,
+        a2_id: &AgentId,
+    ) -> (&'a mut Agent<F::Genome>, &'a mut Agent<F::Genome>) {
         assert_ne!(a1_id, a2_id);
 
-        let a1 = self.agents.get_mut(&a1_id).unwrap() as *mut Agent<N, F>;
-        let a2 = self.agents.get_mut(&a2_id).unwrap() as *mut Agent<N, F>;
+        let a1 = agents.get_mut(a1_id).unwrap() as *mut Agent<F::Genome>;
+        let a2 = agents.get_mut(a2_id).unwrap() as *mut Agent<F::Genome>;
         unsafe { (&mut *a1, &mut *a2) }
     }
 
     fn step(
         &mut self,
-        energy_reproduction_percent: f64,
-        energy_combat: u32,
+        step: u32,
+        energy_policy: &EnergyPolicy,
+        migration_mode: MigrationMode,
+        problem: &Problem<F>,
+        operators: &GeneticOperators<F::Genome>,
     ) {
         let mut to_reproduction = Vec::new();
         let mut to_combat = Vec::new();
-
-        for (&id, agent) in self.agents.iter_mut() {
-            match agent.pick_action(RF::call(agent.energy)) {
+        let mut to_local_search = Vec::new();
+        let mut to_migration = Vec::new();
+        let mut to_custom = Vec::new();
+        let energy_before = self.ledger.as_ref().map(|_| self.energy_sum());
+
+        let island_state = operators.action_selection.as_ref().map(|_| self.state(step));
+        for (&id, agent) in self.agents.iter() {
+            if self.role == IslandRole::Elite {
+                to_combat.push(id);
+                continue;
+            }
+            let action = match (&operators.action_selection, &island_state) {
+                (Some(selection), Some(island_state)) => {
+                    selection.call(&agent.state(step), island_state, &mut self.rng)
+                }
+                _ => {
+                    // The agents below the reproduction threshold don't reproduce, whatever the
+                    // chance function would say.
+                    let reproduction_chance = if agent.energy < energy_policy.reproduction_threshold {
+                        ReproductionChance(0.0)
+                    } else {
+                        RF::with_threshold(agent.energy, energy_policy.reproduction_threshold)
+                    };
+                    agent.pick_action(
+                        reproduction_chance,
+                        migration_mode,
+                        operators.local_search.as_ref(),
+                        &mut self.rng,
+                    )
+                }
+            };
+            match action {
                 Action::Reproduce => to_reproduction.push(id),
                 Action::Combat => to_combat.push(id),
+                Action::LocalSearch => {
+                    assert!(
+                        operators.local_search.is_some(),
+                        "Action::LocalSearch needs a local search, see SystemBuilder::local_search"
+                    );
+                    to_local_search.push(id)
+                }
+                Action::Migrate => to_migration.push(id),
+                Action::Rest => {}
+                Action::Custom(action) => to_custom.push((id, action)),
             }
         }
 
-        self.reproductions(to_reproduction, energy_reproduction_percent);
-        self.combats(to_combat, energy_combat);
-        self.deaths();
+        for id in to_migration {
+            self.emigrate(&id);
+        }
+        self.reproductions(to_reproduction, energy_policy, problem, operators, step);
+        if let Some(local_search) = &operators.local_search {
+            self.local_searches(to_local_search, problem, local_search, step);
+        }
+        if let Some(selection) = &operators.action_selection {
+            for (id, action) in to_custom {
+                let agent = self.agents.get_mut(&id).unwrap();
+                let state = agent.state(step);
+                selection.custom(action, &state, &mut agent.energy, &mut self.rng);
+                if let Some(ledger) = &mut self.ledger {
+                    ledger.step.custom_gained += agent.energy.saturating_sub(state.energy) as u64;
+                    ledger.step.custom_lost += state.energy.saturating_sub(agent.energy) as u64;
+                }
+            }
+        }
+        self.combats(to_combat, energy_policy.combat_transfer, problem, operators, step);
+        self.deaths(energy_policy.death_threshold);
+
+        if let Some(energy_before) = energy_before {
+            let energy_after = self.energy_sum();
+            if let Some(ledger) = &mut self.ledger {
+                ledger.close_step(self.id, step, energy_before, energy_after);
+            }
+        }
+    }
+
+    fn energy_sum(&self) -> u32 {
+        self.agents.values().map(|a| a.energy).sum()
+    }
+
+    /// Removes the agent from the island and puts it into the migration queue.
+    fn emigrate(&mut self, id: &AgentId) {
+        let agent = self.agents.remove(id).unwrap();
+        if let Some(ledger) = &mut self.ledger {
+            ledger.step.migration += agent.energy as u64;
+        }
+        self.migration_queue.push(agent);
+    }
+
+    /// The statistics the agents choose their actions by.
+    fn state(&self, step: u32) -> IslandState {
+        let agents_amount = self.agents.len();
+        let energy_sum = self.agents.values().map(|a| a.energy).sum::<u32>();
+        IslandState {
+            island: self.id,
+            step,
+            agents_amount,
+            energy_sum,
+            best_fitness: self.agents.values().map(|a| a.fitness).fold(f64::INFINITY, f64::min),
+            average_fitness: self.agents.values().map(|a| a.fitness).sum::<f64>() / agents_amount as f64,
+            average_energy: energy_sum as f64 / agents_amount as f64,
+        }
     }
 
-    fn reproductions(&mut self, mut agents: Vec<AgentId>, energy_passed_percent: f64) {
-        agents.shuffle(&mut thread_rng());
+    fn local_searches(
+        &mut self,
+        agents: Vec<AgentId>,
+        problem: &Problem<F>,
+        local_search: &LocalSearchConf<F::Genome>,
+        step: u32,
+    ) {
+        for id in agents {
+            let agent = self.agents.get_mut(&id).unwrap();
+            if agent.energy <= local_search.energy {
+                continue;
+            }
+            agent.energy -= local_search.energy;
+            if let Some(ledger) = &mut self.ledger {
+                ledger.step.local_search += local_search.energy as u64;
+            }
+
+            // The search starts from the genes the agent lives with, whatever it learned before.
+            // It sees the fitness as the combats assess it, and the best genes it evaluated are
+            // kept here, compared like in the combats, instead of evaluating them again.
+            let handling = &problem.constraint_handling;
+            let mut evaluations = 0;
+            let mut best: Option<(F::Genome, Evaluation)> = None;
+            let mut evaluate = |genes: &F::Genome| {
+                evaluations += 1;
+                let evaluation = problem.evaluate(&[genes]).pop().unwrap();
+                let (fitness, violation) = (evaluation.fitness, evaluation.violation);
+                if best.as_ref().is_none_or(|(_, best)| {
+                    handling.compare((fitness, violation), (best.fitness, best.violation), step).is_lt()
+                }) {
+                    best = Some((genes.clone(), evaluation));
+                }
+                handling.assess(fitness, violation, step).0
+            };
+            let fitness = handling.assess(agent.fitness, agent.violation, step).0;
+            local_search.search.call(&agent.genes, fitness, &mut evaluate, &problem.domain, &mut self.rng);
+            self.evaluations += evaluations;
+            self.local_search_evaluations += evaluations;
+            let Some((genes, evaluation)) = best.filter(|(_, best)| {
+                handling.compare((best.fitness, best.violation), agent.fighting_fitness(), step).is_lt()
+            }) else {
+                continue;
+            };
+
+            let newborn = Newborn {
+                genes,
+                energy: agent.energy,
+                id,
+            };
+            let mut improved = Agent::evaluated(newborn, evaluation.clone());
+            improved.prestige = agent.prestige;
+            improved.born = agent.born;
+            improved.cell = agent.cell;
+            match local_search.mode {
+                LocalSearchMode::Lamarckian => *agent = improved.clone(),
+                LocalSearchMode::Baldwinian => agent.learned = Some(evaluation),
+            }
+            self.record(&improved, problem, step);
+        }
+    }
+
+    fn reproductions(
+        &mut self,
+        mut agents: Vec<AgentId>,
+        energy_policy: &EnergyPolicy,
+        problem: &Problem<F>,
+        operators: &GeneticOperators<F::Genome>,
+        step: u32,
+    ) {
+        let mut newborns = Vec::new();
+        let mut cells = Vec::new();
+        let mut occupied = self.occupied_cells();
+        agents.shuffle(&mut self.rng);
         while agents.len() >= 2 {
             let a1_id = agents.pop().unwrap();
-            let a2_id = agents.pop().unwrap();
-
-            let ch1_id = AgentId(self._id, self.new_agent_id());
-            let ch2_id = AgentId(self._id, self.new_agent_id());
+            let a2_id = match &self.lattice {
+                Some(lattice) => {
+                    let Some(&i) = self.neighbour_indexes(lattice, &a1_id, &agents).choose(&mut self.rng) else {
+                        continue;
+                    };
+                    agents.remove(i)
+                }
+                None => agents.pop().unwrap(),
+            };
+            if let Some(lattice) = &self.lattice {
+                // The children are placed in the free cells next to their parents, the parents
+                // don't reproduce if there aren't enough of them.
+                let free: BTreeSet<_> = [a1_id, a2_id]
+                    .iter()
+                    .flat_map(|id| lattice.neighbours(self.agents[id].cell.unwrap()))
+                    .copied()
+                    .filter(|cell| !occupied.contains(cell))
+                    .collect();
+                if free.len() < 2 {
+                    continue;
+                }
+                let chosen = free.into_iter().choose_multiple(&mut self.rng, 2);
+                occupied.extend(&chosen);
+                cells.extend(chosen);
+            }
 
-            let (a1, a2) = self.get_pair_mut(&a1_id, &a2_id);
+            let ch1_id = AgentId(self.id, self.new_agent_id());
+            let ch2_id = AgentId(self.id, self.new_agent_id());
 
+            let (a1, a2) = Self::get_pair_mut(&mut self.agents, &a1_id, &a2_id);
 
             let offspring = a1.reproduce(
                 a2,
-                energy_passed_percent,
-                ch1_id.clone(),
-                ch2_id.clone(),
+                energy_policy,
+                problem,
+                operators,
+                (ch1_id, ch2_id),
+                &mut self.rng,
             );
-
-            if offspring.0.fitness < self.historical_best.fitness {
-                self.historical_best = offspring.0.clone();
+            if let Some(ledger) = &mut self.ledger {
+                ledger.step.reproduction += offspring.iter().map(|child| child.energy as u64).sum::<u64>();
             }
-            if offspring.1.fitness < self.historical_best.fitness {
-                self.historical_best = offspring.1.clone();
-            }
-            self.agents.insert(ch1_id, offspring.0);
-            self.agents.insert(ch2_id, offspring.1);
+            newborns.extend(offspring);
+        }
+
+        if newborns.is_empty() {
+            return;
+        }
+        let mut cells = cells.into_iter();
+        let (children, evaluations) = Agent::evaluate(newborns, problem);
+        self.evaluations += evaluations;
+        for mut child in children {
+            child.born = step;
+            child.cell = cells.next();
+            self.record(&child, problem, step);
+            self.agents.insert(child.id, child);
         }
     }
 
-    fn combats(&mut self, mut agents: Vec<AgentId>, energy: u32) {
-        agents.shuffle(&mut thread_rng());
+    fn combats(
+        &mut self,
+        mut agents: Vec<AgentId>,
+        transfer: CombatTransfer,
+        problem: &Problem<F>,
+        operators: &GeneticOperators<F::Genome>,
+        step: u32,
+    ) {
+        let crowding: BTreeMap<AgentId, f64> = if problem.pareto.is_some() {
+            let points: Vec<_> = self.agents.values().map(|a| a.objectives()).collect();
+            self.agents.keys().copied().zip(crowding_distances(&points)).collect()
+        } else {
+            BTreeMap::new()
+        };
+
+        let handling = &problem.constraint_handling;
+        agents.shuffle(&mut self.rng);
         while agents.len() >= 2 {
             let a1_id = agents.pop().unwrap();
-            let a2_id = agents.pop().unwrap();
+            // On a lattice only the neighbours can fight.
+            let neighbours = self.lattice.as_ref().map(|lattice| self.neighbour_indexes(lattice, &a1_id, &agents));
+            let a2_id = match &operators.partner_selection {
+                Some(selection) => {
+                    let indexes = neighbours.unwrap_or_else(|| (0..agents.len()).collect());
+                    if indexes.is_empty() {
+                        continue;
+                    }
+                    let candidate = |id: &AgentId| {
+                        let agent = &self.agents[id];
+                        let combatant = agent.combatant(0.0, handling, step);
+                        Candidate {
+                            genes: &agent.genes,
+                            energy: agent.energy,
+                            fitness: combatant.fitness,
+                            violation: combatant.violation,
+                        }
+                    };
+                    let candidates: Vec<_> = indexes.iter().map(|&i| candidate(&agents[i])).collect();
+                    match selection.call(&candidate(&a1_id), &candidates, &mut self.rng) {
+                        Some(i) => agents.remove(indexes[i]),
+                        None => continue,
+                    }
+                }
+                None => match neighbours {
+                    Some(neighbours) => match neighbours.choose(&mut self.rng) {
+                        Some(&i) => agents.remove(i),
+                        None => continue,
+                    },
+                    None => agents.pop().unwrap(),
+                },
+            };
 
-            let (a1, a2) = self.get_pair_mut(&a1_id, &a2_id);
+            let (a1, a2) = Self::get_pair_mut(&mut self.agents, &a1_id, &a2_id);
 
-            a1.combat(a2, energy, CF::call);
+            let win_chance = CF::call(
+                &a1.combatant(crowding.get(&a1_id).copied().unwrap_or(0.0), handling, step),
+                &a2.combatant(crowding.get(&a2_id).copied().unwrap_or(0.0), handling, step),
+            );
+            let transferred = a1.combat(a2, transfer, win_chance, &mut self.rng);
+            if let Some(ledger) = &mut self.ledger {
+                ledger.step.combat += transferred as u64;
+            }
         }
     }
 
-    fn deaths(&mut self) {
+    fn deaths(&mut self, threshold: u32) {
         let to_remove: Vec<_> = self
             .agents
             .iter()
-            .filter(|(_, agent)| agent.energy == 0)
-            .map(|(id, _)| id.clone())
+            .filter(|(_, agent)| agent.energy <= threshold)
+            .map(|(id, _)| *id)
             .collect();
 
         for id in to_remove.iter() {
-            self.agents.remove(id);
+            let agent = self.agents.remove(id).unwrap();
+            if let Some(ledger) = &mut self.ledger {
+                ledger.step.death += agent.energy as u64;
+            }
+        }
+    }
+
+    fn log(&self, direction: Direction) -> IslandRecord {
+        let agents_amount = self.agents.len();
+        let energy_sum = self.agents.values().map(|a| a.energy).sum::<u32>();
+        let best_living = self.agents
+            .values()
+            .map(|a| a.fitness)
+            .min_by(|f1, f2| f1.partial_cmp(f2).unwrap())
+            .map(|f| direction.minimized(f));
+        let (average_fitness, average_energy) = if agents_amount > 0 {
+            (
+                Some(direction.minimized(self.agents.values().map(|a| a.fitness).sum::<f64>() / agents_amount as f64)),
+                Some(energy_sum as f64 / agents_amount as f64),
+            )
+        } else {
+            (None, None)
+        };
+
+        let (occupancy, neighbour_fitness_difference, fitness_autocorrelation) = match &self.lattice {
+            Some(lattice) => {
+                let mut fitness = vec![None; lattice.cells()];
+                for agent in self.agents.values() {
+                    fitness[agent.cell.unwrap()] = Some(direction.minimized(agent.fitness));
+                }
+                let (occupancy, difference, autocorrelation) = lattice.diversity(&fitness);
+                (Some(occupancy), difference, autocorrelation)
+            }
+            None => (None, None, None),
+        };
+
+        IslandRecord {
+            island: self.id,
+            agents_amount,
+            energy_sum,
+            best_living,
+            average_fitness,
+            average_energy,
+            occupancy,
+            neighbour_fitness_difference,
+            fitness_autocorrelation,
         }
     }
 
-    fn step_migrations(&mut self, best_amount: usize, elite_amount: usize) {
+    fn step_migrations(&mut self, best_amount: usize, elite_amount: usize, problem: &Problem<F>, step: u32) {
         let mut candidates: Vec<_> = self.agents.keys().copied().collect::<Vec<_>>();
-        candidates.sort_by(|a1, a2| self.agents.get(a1).unwrap().energy.cmp(&self.agents.get(a2).unwrap().energy));
+        candidates.sort_by(|a1, a2| self.agents[a1].compare(&self.agents[a2], &problem.constraint_handling, step));
         let best_amount = best_amount.min(candidates.len());
         let elite_amount = elite_amount.min(best_amount);
         let best = &candidates[..best_amount];
 
-        let elite = best.iter().choose_multiple(&mut thread_rng(), elite_amount);
+        let elite: Vec<_> = best.iter().copied().choose_multiple(&mut self.rng, elite_amount);
         for id in elite {
-            self.migration_queue.push(self.agents.remove(id).unwrap());
+            self.emigrate(&id);
         }
     }
 }
 
 
 #[derive(Debug)]
-pub struct System<const N: usize, F, CF, RF>
+pub struct System<F, CF, RF>
     where
-        F: FitnessFn<N>,
+        F: FitnessFn,
         CF: CombatWinChanceFn,
         RF: ReproductionChanceFn
 {
-    islands: Vec<Island<N, F, CF, RF>>,
+    islands: Vec<Island<F, CF, RF>>,
     steps: u32,
-    energy_reproduction_percent: f64,
-    energy_combat: u32,
+    energy_policy: EnergyPolicy,
     migration_steps: u32,
     migrations_best_amount: usize,
     migrations_elite_amount: usize,
-    logs: Vec<String>,
+    migration_targets: Vec<Vec<usize>>,
+    migrant_distribution: MigrantDistribution,
+    migration_mode: MigrationMode,
+    /// The prestige needed to move to the elite island, which is the last island, if there is one.
+    elite_prestige: Option<u32>,
+    energy_validation: Option<EnergyValidation>,
+    log_sink: Box<dyn LogSink>,
     log_steps: u32,
-    f_phantom: PhantomData<F>,
+    problem: Problem<F>,
+    operators: GeneticOperators<F::Genome>,
+    termination: TerminationCriteria,
+    checkpoint: Option<CheckpointConf>,
+    rng: ChaCha8Rng,
+    thread_pool: ThreadPool,
+    /// The next step to be made, the run is resumed from it.
+    step: u32,
+    elapsed: Duration,
+    last_improvement: u32,
 }
 
-impl<const N: usize, F, CF, RF> System<N, F, CF, RF>
+impl<F, CF, RF> System<F, CF, RF>
     where
-        F: FitnessFn<N>,
+        F: FitnessFn,
         CF: CombatWinChanceFn,
         RF: ReproductionChanceFn
 {
-    fn log(&mut self, start: Instant) -> String {
-        let timestamp = start.elapsed().as_secs_f32();
-        let historical_best = F::call(&self.best_sol());
+    fn log(&self, step: u32, start: Instant) -> LogRecord {
+        let timestamp = start.elapsed().as_secs_f64();
+        let direction = self.problem.direction;
+        let historical_best = direction.minimized(self.historical_best().fitness);
         let agents_amount = self.islands
             .iter()
             .map(|i| i.agents.len())
@@ -341,105 +982,406 @@ impl<const N: usize, F, CF, RF> System<N, F, CF, RF>
                 i.agents.values().map(|a| a.energy).sum::<u32>()
             )
             .sum::<u32>();
-        let best_living = self.islands
-            .iter()
-            .flat_map(|i| i.agents.values())
-            .min_by(|a1, a2| a1.fitness.partial_cmp(&a2.fitness).unwrap())
-            .unwrap()
-            .fitness;
+        let agents: Vec<_> = self.islands.iter().flat_map(|i| i.agents.values()).collect();
+        let (best_living, average_fitness, average_energy, feasibility_ratio) = if agents_amount > 0 {
+            let best_living = agents.iter().map(|a| a.fitness).min_by(|f1, f2| f1.partial_cmp(f2).unwrap());
+            let average_fitness = agents.iter().map(|a| a.fitness).sum::<f64>() / agents_amount as f64;
+            let average_energy = energy_sum as f64 / agents_amount as f64;
+            let feasible = agents.iter().filter(|a| a.violation == 0.0).count();
+            (
+                best_living.map(|f| direction.minimized(f)),
+                Some(direction.minimized(average_fitness)),
+                Some(average_energy),
+                Some(feasible as f64 / agents_amount as f64),
+            )
+        } else {
+            (None, None, None, None)
+        };
 
-        let average_fitness = self.islands
-            .iter()
-            .flat_map(|i| i.agents.values())
-            .map(|a| a.fitness)
-            .sum::<f64>() / agents_amount as f64;
+        let pareto_archive = self.pareto_archive();
+
+        LogRecord {
+            step,
+            timestamp,
+            historical_best,
+            agents_amount,
+            energy_sum,
+            best_living,
+            average_fitness,
+            average_energy,
+            pareto_front_size: pareto_archive.as_ref().map(|archive| archive.solutions().len()),
+            hypervolume: pareto_archive
+                .as_ref()
+                .zip(self.problem.pareto.as_ref())
+                .map(|(archive, pareto)| archive.hypervolume(&pareto.reference_point)),
+            feasibility_ratio,
+            best_feasible: self.best_feasible().map(|a| direction.minimized(a.fitness)),
+            local_search_evaluations: self.islands.iter().map(|i| i.local_search_evaluations).sum(),
+            energy_flows: self.energy_validation.map(|_| self.energy_flows()),
+            islands: self.islands.iter().map(|i| i.log(direction)).collect(),
+        }
+    }
+
+    /// The energy flows of all islands since the start of the run, empty unless the system was
+    /// built with [`SystemBuilder::energy_ledger`].
+    pub fn energy_flows(&self) -> EnergyFlows {
+        self.islands.iter().filter_map(|i| i.ledger.as_ref()).map(|ledger| ledger.total).sum()
+    }
+
+    /// Merges the archives of all islands.
+    fn pareto_archive(&self) -> Option<ParetoArchive<F::Genome>> {
+        let pareto = self.problem.pareto.as_ref()?;
+        let mut merged = ParetoArchive::new(pareto.archive_capacity);
+        for archive in self.islands.iter().filter_map(|i| i.archive.as_ref()) {
+            for solution in archive.solutions() {
+                merged.insert(&solution.genes, &solution.objectives);
+            }
+        }
+        Some(merged)
+    }
+
+    /// The approximation of the Pareto front found so far, empty unless the system was built
+    /// with [`SystemBuilder::pareto`].
+    pub fn pareto_front(&self) -> Vec<ParetoSolution<F::Genome>> {
+        let direction = self.problem.direction;
+        self.pareto_archive()
+            .map(|archive| {
+                archive
+                    .solutions()
+                    .iter()
+                    .map(|s| ParetoSolution {
+                        genes: s.genes.clone(),
+                        objectives: s.objectives.iter().map(|&o| direction.minimized(o)).collect(),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
 
-        let average_energy = self.islands
+    /// The energy of all the agents, including the ones waiting for the migration.
+    fn energy_sum(&self) -> u64 {
+        self.islands
             .iter()
-            .flat_map(|i| i.agents.values())
-            .map(|a| a.energy)
-            .sum::<u32>() as f64 / agents_amount as f64;
+            .flat_map(|i| i.agents.values().chain(&i.migration_queue))
+            .map(|a| a.energy as u64)
+            .sum()
+    }
 
-        format!("{},{},{},{},{},{},{}\n", timestamp, historical_best, agents_amount, energy_sum, best_living, average_fitness, average_energy)
+    /// Whether the agents may decide to emigrate themselves, in which case they are delivered at
+    /// the end of every step.
+    fn agents_emigrate(&self) -> bool {
+        matches!(self.migration_mode, MigrationMode::AgentInitiated { .. })
+            || self.operators.action_selection.is_some()
     }
 
     fn migrate_agents(&mut self) {
-        let mut rng = thread_rng();
-        let len = self.islands.len();
+        let rng = &mut self.rng;
 
         let mut push_queue = Vec::new();
 
-        for (i, island) in self.islands.iter_mut().enumerate() {
+        for (i, (island, targets)) in self.islands.iter_mut().zip(&self.migration_targets).enumerate() {
+            if targets.is_empty() {
+                push_queue.extend(island.migration_queue.drain(..).map(|agent| (i, i, agent)));
+                continue;
+            }
+
+            let offset = match self.migrant_distribution {
+                MigrantDistribution::Random => 0,
+                MigrantDistribution::Even => rng.gen_range(0..targets.len()),
+            };
+            let mut k = 0;
             while let Some(agent) = island.migration_queue.pop() {
-                let mut new = rng.gen_range(0..len);
-                while new == i {
-                    new = rng.gen_range(0..len);
+                let new = match self.migrant_distribution {
+                    MigrantDistribution::Random => targets[rng.gen_range(0..targets.len())],
+                    MigrantDistribution::Even => targets[(offset + k) % targets.len()],
+                };
+                k += 1;
+                push_queue.push((i, new, agent));
+            }
+        }
+
+        for (from, to, mut agent) in push_queue {
+            let island = &mut self.islands[to];
+            agent.cell = None;
+            if let Some(cells) = island.lattice.as_ref().map(Grid::cells) {
+                let occupied = island.occupied_cells();
+                let free: Vec<_> = (0..cells).filter(|cell| !occupied.contains(cell)).collect();
+                match free.choose(rng) {
+                    Some(&cell) => agent.cell = Some(cell),
+                    None => {
+                        // The migrant waits for the next migration on its island.
+                        self.islands[from].migration_queue.push(agent);
+                        continue;
+                    }
                 }
-                push_queue.push((new, agent));
             }
+            island.agents.insert(agent.id, agent);
         }
+    }
 
-        for (island_id, agent) in push_queue {
-            self.islands[island_id].agents.insert(agent.id, agent);
+    /// Moves the agents of the regular islands with at least `prestige` won combats to the elite
+    /// island.
+    fn promote_agents(&mut self, prestige: u32) {
+        let (elite, regular) = self.islands.split_last_mut().unwrap();
+        for island in regular {
+            let promoted: Vec<_> = island
+                .agents
+                .iter()
+                .filter(|(_, agent)| agent.prestige >= prestige)
+                .map(|(&id, _)| id)
+                .collect();
+            for id in promoted {
+                let mut agent = island.agents.remove(&id).unwrap();
+                agent.cell = None;
+                if let Some(ledger) = &mut island.ledger {
+                    ledger.step.migration += agent.energy as u64;
+                }
+                elite.agents.insert(id, agent);
+            }
         }
     }
 
-    pub fn best_sol(&self) -> [f64; N] {
+    /// The agents of the elite island, from the best one, or nothing if the system was built
+    /// without [`SystemBuilder::elite_island`].
+    pub fn elite(&self) -> Vec<EliteSolution<F::Genome>> {
+        let Some(elite) = self.islands.iter().find(|i| i.role == IslandRole::Elite) else {
+            return Vec::new();
+        };
+        let mut agents: Vec<_> = elite.agents.values().collect();
+        agents.sort_by(|a1, a2| a1.compare(a2, &self.problem.constraint_handling, self.step));
+        let direction = self.problem.direction;
+        agents
+            .into_iter()
+            .map(|agent| EliteSolution {
+                genes: agent.genes.clone(),
+                fitness: direction.minimized(agent.fitness),
+                objectives: agent.objectives.iter().map(|&o| direction.minimized(o)).collect(),
+                prestige: agent.prestige,
+            })
+            .collect()
+    }
+
+    fn historical_best(&self) -> &Agent<F::Genome> {
+        let handling = &self.problem.constraint_handling;
         self.islands
             .iter()
-            .min_by(|island1, island2| {
-                island1
-                    .historical_best
-                    .fitness
-                    .partial_cmp(&island2.historical_best.fitness)
-                    .unwrap()
-            })
+            .filter_map(|island| island.historical_best.as_ref())
+            .min_by(|a1, a2| a1.compare(a2, handling, self.step))
             .unwrap()
-            .historical_best
-            .genes
     }
 
-    pub fn run(&mut self) -> [f64; N] {
-        fs::remove_file("outputs.csv").unwrap();
-        let mut f = File::create("outputs.csv").unwrap();
-        f.write_all(
-            self.logs
-                .iter()
-                .fold(String::new(), |mut s, i| {
-                    s.push_str(i);
-                    s
-                })
-                .as_bytes()
-        ).unwrap();
-
-        let start = Instant::now();
-        for i in 0..self.steps {
-            for island in self.islands.iter_mut() {
-                island.step(
-                    self.energy_reproduction_percent,
-                    self.energy_combat,
-                );
+    fn best_feasible(&self) -> Option<&Agent<F::Genome>> {
+        self.islands
+            .iter()
+            .filter_map(|island| island.best_feasible.as_ref())
+            .min_by(|a1, a2| a1.fitness.partial_cmp(&a2.fitness).unwrap())
+    }
+
+    /// The fitness the termination criteria are checked against.
+    fn best_feasible_fitness(&self) -> f64 {
+        self.best_feasible().map_or(f64::INFINITY, |a| a.fitness)
+    }
+
+    pub fn best_sol(&self) -> F::Genome {
+        self.historical_best().genes.clone()
+    }
+
+    fn evaluations(&self) -> u64 {
+        self.islands.iter().map(|i| i.evaluations).sum()
+    }
+
+    /// The amount of the agents, including the ones waiting for the migration.
+    fn living_agents_amount(&self) -> usize {
+        self.islands.iter().map(|i| i.agents.len() + i.migration_queue.len()).sum()
+    }
+
+    /// Saves the state of the system, so that the run can be continued with
+    /// [`System::load_checkpoint`], e.g. after the process is restarted.
+    ///
+    /// The state of the fitness function isn't saved. The checkpoint is written to a temporary
+    /// file next to `path` first and then moved over it, so an interrupted save leaves the
+    /// previous checkpoint intact.
+    pub fn save_checkpoint(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let checkpoint = Checkpoint {
+            step: self.step,
+            elapsed: self.elapsed,
+            last_improvement: self.last_improvement,
+            rng: self.rng.clone(),
+            migration_targets: self.migration_targets.clone(),
+            islands: self.islands.iter().map(|i| i.checkpoint()).collect(),
+        };
+        let path = path.as_ref();
+        let mut temp_path = path.as_os_str().to_owned();
+        temp_path.push(".tmp");
+        let mut writer = BufWriter::new(File::create(&temp_path)?);
+        serde_json::to_writer(&mut writer, &checkpoint)?;
+        writer.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+        fs::rename(&temp_path, path)
+    }
+
+    /// Restores the state saved with [`System::save_checkpoint`]. The system has to be built
+    /// with the same configuration as the saved one, then [`System::run`] continues the run
+    /// exactly as it would have gone on without the interruption (apart from the timestamps).
+    /// Fails with [`io::ErrorKind::InvalidData`] if the islands of the checkpoint don't match
+    /// the ones of the system.
+    pub fn load_checkpoint(&mut self, path: impl AsRef<Path>) -> io::Result<()> {
+        let reader = BufReader::new(File::open(path)?);
+        let checkpoint: Checkpoint<F::Genome> = serde_json::from_reader(reader)?;
+        if checkpoint.islands.len() != self.islands.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "The checkpoint has {} islands, but the system has {}",
+                    checkpoint.islands.len(),
+                    self.islands.len()
+                ),
+            ));
+        }
+        let regular_islands = self.migration_targets.len();
+        if checkpoint.migration_targets.len() != regular_islands
+            || checkpoint.migration_targets.iter().flatten().any(|&target| target >= regular_islands)
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("The migration topology of the checkpoint doesn't fit {regular_islands} islands"),
+            ));
+        }
+
+        self.step = checkpoint.step;
+        self.elapsed = checkpoint.elapsed;
+        self.last_improvement = checkpoint.last_improvement;
+        self.rng = checkpoint.rng;
+        self.migration_targets = checkpoint.migration_targets;
+        for (island, island_checkpoint) in self.islands.iter_mut().zip(checkpoint.islands) {
+            island.restore(island_checkpoint);
+        }
+        Ok(())
+    }
+
+    pub fn run(&mut self) -> RunResult<F::Genome> {
+        let start = Instant::now().checked_sub(self.elapsed).unwrap_or_else(Instant::now);
+        let mut tracker = TerminationTracker::new(
+            self.termination,
+            self.steps,
+            start,
+            self.best_feasible_fitness(),
+            self.last_improvement,
+        );
+        let mut i = self.step;
+        // A run without any steps to make, or resumed after it already stopped, stops right away.
+        let mut termination =
+            tracker.check(i, self.best_feasible_fitness(), self.evaluations(), self.living_agents_amount());
+        while termination.is_none() {
+            i = self.advance(i);
+
+            if i.is_multiple_of(self.log_steps) {
+                let record = self.log(i, start);
+                self.log_sink.log(&record).expect("Can't write the logs");
             }
+            i += 1;
+
+            let best_fitness = self.best_feasible_fitness();
+            termination = tracker.check(i, best_fitness, self.evaluations(), self.living_agents_amount());
+            self.step = i;
+            self.elapsed = start.elapsed();
+            self.last_improvement = tracker.last_improvement();
 
-            if i % self.migration_steps == 0 {
-                for island in self.islands.iter_mut() {
-                    island.step_migrations(self.migrations_best_amount, self.migrations_elite_amount);
+            if let Some(checkpoint) = &self.checkpoint {
+                if i.is_multiple_of(checkpoint.steps) {
+                    self.save_checkpoint(&checkpoint.path).expect("Can't write the checkpoint");
                 }
-                self.migrate_agents();
             }
+        }
+
+        let best = self.historical_best();
+        let direction = self.problem.direction;
+        RunResult {
+            best_sol: best.genes.clone(),
+            best_fitness: direction.minimized(best.fitness),
+            steps: i,
+            evaluations: self.evaluations(),
+            elapsed: start.elapsed(),
+            termination: termination.unwrap(),
+            best_feasible_sol: self.best_feasible().map(|a| a.genes.clone()),
+            best_feasible_fitness: self.best_feasible().map(|a| direction.minimized(a.fitness)),
+            pareto_front: self.pareto_front(),
+            elite: self.elite(),
+        }
+    }
+
+    /// Steps all islands in parallel, starting from step `from`, up to the first step after which
+    /// they have to be synchronised, i.e. when migration or logging happens, or after every step
+    /// if the termination criteria require it or the agents may emigrate on their own. Returns
+    /// that step, or the step in which the last agents died.
+    fn advance(&mut self, from: u32) -> u32 {
+        let mut to = from;
+        while to + 1 < self.steps
+            && !self.termination.checked_every_step()
+            && !self.agents_emigrate()
+            && !to.is_multiple_of(self.migration_steps)
+            && !to.is_multiple_of(self.log_steps)
+            && !self.checkpoint.as_ref().is_some_and(|c| (to + 1).is_multiple_of(c.steps))
+        {
+            to += 1;
+        }
 
-            if i % self.log_steps == 0 {
-                f.write_all(self.log(start).as_bytes()).expect("Can't write logs to the log file");
+        let islands = &mut self.islands;
+        let energy_policy = &self.energy_policy;
+        let problem = &self.problem;
+        let migration_mode = self.migration_mode;
+        let operators = &self.operators;
+        // The step after which every island was left without agents, if they all were. Nothing
+        // can happen on an empty island until the next migration, so the run died out then.
+        let emptied: Vec<Option<u32>> = self.thread_pool.install(|| {
+            islands
+                .par_iter_mut()
+                .map(|island| {
+                    let mut emptied = None;
+                    for step in from..=to {
+                        island.step(step, energy_policy, migration_mode, problem, operators);
+                        if island.agents.is_empty() && island.migration_queue.is_empty() {
+                            emptied.get_or_insert(step);
+                        }
+                    }
+                    emptied
+                })
+                .collect()
+        });
+        // Collected into a `Vec` first: collecting the parallel iterator into an `Option` would
+        // stop stepping the other islands once one of them wasn't emptied.
+        let extinction = emptied.into_iter().collect::<Option<Vec<_>>>().and_then(|e| e.into_iter().max());
+        if let Some(extinction) = extinction {
+            return extinction;
+        }
+
+        let migration_step = to.is_multiple_of(self.migration_steps);
+        if migration_step || self.agents_emigrate() {
+            let energy_before = self.energy_validation.map(|_| self.energy_sum());
+            if migration_step && self.migration_mode == MigrationMode::Batch {
+                for island in self.islands.iter_mut().filter(|i| i.role == IslandRole::Regular) {
+                    island.step_migrations(self.migrations_best_amount, self.migrations_elite_amount, &self.problem, to);
+                }
+            }
+            self.migrate_agents();
+            if let Some(prestige) = self.elite_prestige.filter(|_| migration_step) {
+                self.promote_agents(prestige);
+            }
+            if let Some((validation, energy_before)) = self.energy_validation.zip(energy_before) {
+                validation.check(energy_before as i64, self.energy_sum() as i64, || {
+                    format!("by the migrations in step {}", to)
+                });
+                for ledger in self.islands.iter_mut().filter_map(|i| i.ledger.as_mut()) {
+                    ledger.flush();
+                }
             }
         }
 
-        self.best_sol()
+        to
     }
 }
 
 pub struct SystemBuilder<
-    const N: usize,
-    F: FitnessFn<N>,
+    F: FitnessFn,
     CF: CombatWinChanceFn = DefaultCombatWinChanceFn,
     RF: ReproductionChanceFn = DefaultReproductionChanceFn,
 > {
@@ -447,38 +1389,99 @@ pub struct SystemBuilder<
     steps: u32,
     agents_per_island: usize,
     agent_energy: u32,
-    energy_passed_on_reproduction: f64,
-    energy_combat: u32,
+    energy_policy: EnergyPolicy,
     migration_steps: u32,
     migrations_best_amount: usize,
     migrations_elite_amount: usize,
+    migration_topology: Box<dyn MigrationTopology>,
+    migrant_distribution: MigrantDistribution,
+    migration_mode: MigrationMode,
+    elite_prestige: Option<u32>,
+    energy_validation: Option<EnergyValidation>,
+    lattice: Option<Lattice>,
+    local_search_chance: f64,
+    local_search_energy: u32,
     log_steps: u32,
-    f_phantom: PhantomData<F>,
+    log_sink: Box<dyn LogSink>,
+    checkpoint: Option<CheckpointConf>,
+    pareto_reference_point: Option<Vec<f64>>,
+    pareto_archive_capacity: usize,
+    direction: Direction,
+    constraint_handling: ConstraintHandling,
+    equality_tolerance: f64,
+    seed: Option<u64>,
+    threads: Option<usize>,
+    termination: TerminationCriteria,
+    fitness: F,
+    operators: GeneticOperators<F::Genome>,
     cf_phantom: PhantomData<CF>,
     rf_phantom: PhantomData<RF>,
 }
 
-impl<const N: usize, F, CF, RF> SystemBuilder<N, F, CF, RF>
+impl<F, CF, RF> Default for SystemBuilder<F, CF, RF>
     where
-        F: FitnessFn<N>,
+        F: FitnessFn + Default,
         CF: CombatWinChanceFn,
         RF: ReproductionChanceFn
 {
-    pub fn new() -> Self {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<F, CF, RF> SystemBuilder<F, CF, RF>
+    where
+        F: FitnessFn,
+        CF: CombatWinChanceFn,
+        RF: ReproductionChanceFn
+{
+    pub fn new() -> Self
+        where
+            F: Default
+    {
+        Self::with_fitness(F::default())
+    }
+
+    pub fn with_fitness(fitness: F) -> Self {
         SystemBuilder {
             island_amount: 5,
             agents_per_island: 100,
             steps: 10_000,
             agent_energy: 10,
-            energy_passed_on_reproduction: 0.25,
-            energy_combat: 2,
+            energy_policy: EnergyPolicy::default(),
             migration_steps: 50,
             migrations_best_amount: 10,
             migrations_elite_amount: 5,
+            migration_topology: Box::new(FullyConnected),
+            migrant_distribution: MigrantDistribution::Random,
+            migration_mode: MigrationMode::Batch,
+            elite_prestige: None,
+            energy_validation: None,
+            lattice: None,
             log_steps: 100,
-            f_phantom: PhantomData::default(),
-            cf_phantom: PhantomData::default(),
-            rf_phantom: PhantomData::default(),
+            log_sink: Box::new(NoLog),
+            checkpoint: None,
+            pareto_reference_point: None,
+            pareto_archive_capacity: 100,
+            direction: Direction::Minimize,
+            constraint_handling: ConstraintHandling::default(),
+            equality_tolerance: 1e-4,
+            seed: None,
+            threads: None,
+            termination: TerminationCriteria::default(),
+            operators: GeneticOperators {
+                recombination: F::Genome::default_recombination(),
+                mutation: F::Genome::default_mutation(),
+                repair: None,
+                local_search: None,
+                action_selection: None,
+                partner_selection: None,
+            },
+            local_search_chance: 0.1,
+            local_search_energy: 1,
+            fitness,
+            cf_phantom: PhantomData,
+            rf_phantom: PhantomData,
         }
     }
 
@@ -504,12 +1507,24 @@ impl<const N: usize, F, CF, RF> SystemBuilder<N, F, CF, RF>
 
     pub fn energy_passed_on_reproduction(mut self, ratio: f64) -> Self {
         assert!(0.0 < ratio && ratio <= 1.0);
-        self.energy_passed_on_reproduction = ratio;
+        self.energy_policy.reproduction_transfer = ratio;
         self
     }
 
+    /// The fixed energy transferred in a combat, see [`EnergyPolicy::combat_transfer`].
     pub fn combat_energy(mut self, amount: u32) -> Self {
-        self.energy_combat = amount;
+        self.energy_policy.combat_transfer = CombatTransfer::Fixed(amount);
+        self
+    }
+
+    /// The thresholds and the transfer rules of the energy, replacing the ones set with
+    /// [`Self::energy_passed_on_reproduction`] and [`Self::combat_energy`].
+    pub fn energy_policy(mut self, policy: EnergyPolicy) -> Self {
+        assert!(0.0 < policy.reproduction_transfer && policy.reproduction_transfer <= 1.0);
+        if let CombatTransfer::Proportional(share) = policy.combat_transfer {
+            assert!((0.0..=1.0).contains(&share));
+        }
+        self.energy_policy = policy;
         self
     }
 
@@ -533,34 +1548,1325 @@ impl<const N: usize, F, CF, RF> SystemBuilder<N, F, CF, RF>
         self
     }
 
-    pub fn build(self) -> System<N, F, CF, RF> {
-        for (i, (d_min, d_max)) in F::DOMAIN.iter().enumerate() {
-            if d_min > d_max {
-                panic!("In the domain in argument {}, the first element is larger than the second element, which is not allowed", i)
-            }
-        }
-
-        let islands = (0..self.island_amount)
-            .map(|id| Island::new(self.agents_per_island, self.agent_energy, id))
+    /// Stops the run once the best feasible solution is at most `fitness`, or at least `fitness`
+    /// if the system maximizes. It is checked after every step, like all the termination
+    /// criteria, so the run stops right after the step in which the target was reached.
+    pub fn target_fitness(mut self, fitness: f64) -> Self {
+        self.termination.target_fitness = Some(fitness);
+        self
+    }
+
+    /// Stops the run once the best feasible solution didn't improve for `amount` steps.
+    pub fn stagnation_steps(mut self, amount: u32) -> Self {
+        self.termination.stagnation_steps = Some(amount);
+        self
+    }
+
+    /// Stops the run once it took `limit`, counting the time before a resumed checkpoint. The
+    /// limit is checked after every step, so the run stops at most one step too late.
+    pub fn time_limit(mut self, limit: Duration) -> Self {
+        self.termination.time_limit = Some(limit);
+        self
+    }
+
+    /// Stops the run once the fitness function was evaluated `amount` times, an evaluation of
+    /// the objectives of a multi-objective system counting as one. The budget is checked after
+    /// every step, so the run overshoots it by at most the evaluations of its last step.
+    pub fn max_evaluations(mut self, amount: u64) -> Self {
+        self.termination.max_evaluations = Some(amount);
+        self
+    }
+
+    /// Where the records logged every `log_steps` steps go, by default they are dropped.
+    ///
+    /// Systems used to write them to `outputs.csv` unless told otherwise. That file is no longer
+    /// created, so a run that is plotted with `plotting/live_plotting.py` needs
+    /// `.log_sink(CsvLog::create("outputs.csv")?)` now.
+    pub fn log_sink(mut self, sink: impl LogSink + 'static) -> Self {
+        self.log_sink = Box::new(sink);
+        self
+    }
+
+    /// Saves the state of the system to `path` every `steps` steps of the run, overwriting the
+    /// previous checkpoint. See [`System::load_checkpoint`].
+    pub fn checkpoint(mut self, path: impl Into<PathBuf>, steps: u32) -> Self {
+        assert!(steps > 0);
+        self.checkpoint = Some(CheckpointConf {
+            path: path.into(),
+            steps,
+        });
+        self
+    }
+
+    /// Makes the system multi-objective: the agents are evaluated with
+    /// [`FitnessFn::objectives`], their fitness is [`FitnessFn::scalarize`]d from the objectives,
+    /// and the non-dominated ones are archived. The hypervolume of the
+    /// archive, bounded by the reference point, is logged. The reference point has to be worse
+    /// than the solutions in every objective, i.e. greater if the system minimizes and lower if
+    /// it maximizes.
+    ///
+    /// Use it together with [`ParetoCombatWinChanceFn`] for the Pareto dominance based combat.
+    pub fn pareto(mut self, reference_point: Vec<f64>) -> Self {
+        self.pareto_reference_point = Some(reference_point);
+        self
+    }
+
+    /// The maximal amount of the solutions kept in the Pareto archive, 100 by default.
+    pub fn pareto_archive_capacity(mut self, capacity: usize) -> Self {
+        assert!(capacity > 0);
+        self.pareto_archive_capacity = capacity;
+        self
+    }
+
+    /// Whether the fitness, and the objectives of a multi-objective problem, are minimized, the
+    /// default, or maximized. The logs and the results report the values as returned by the
+    /// fitness function.
+    pub fn direction(mut self, direction: Direction) -> Self {
+        self.direction = direction;
+        self
+    }
+
+    /// How the agents violating [`FitnessFn::constraints`] are treated, by default with the
+    /// feasibility rules.
+    pub fn constraint_handling(mut self, handling: ConstraintHandling) -> Self {
+        self.constraint_handling = handling;
+        self
+    }
+
+    /// How far from zero an equality constraint may be and still be satisfied, `1e-4` by default.
+    pub fn equality_tolerance(mut self, tolerance: f64) -> Self {
+        assert!(tolerance >= 0.0);
+        self.equality_tolerance = tolerance;
+        self
+    }
+
+    pub fn migration_topology(mut self, topology: impl MigrationTopology + 'static) -> Self {
+        self.migration_topology = Box::new(topology);
+        self
+    }
+
+    pub fn migrant_distribution(mut self, distribution: MigrantDistribution) -> Self {
+        self.migrant_distribution = distribution;
+        self
+    }
+
+    /// Whether the migrants are chosen every `migration_steps` steps, the default, or the agents
+    /// decide to emigrate themselves. Either way they go to the neighbours of their island in the
+    /// migration topology.
+    pub fn migration_mode(mut self, mode: MigrationMode) -> Self {
+        self.migration_mode = mode;
+        self
+    }
+
+    /// Adds the elite island of the elitist EMAS. At every migration the agents of the regular
+    /// islands which won at least `prestige` combats move there, where they don't reproduce and
+    /// only fight each other, so that the best of them survive. Its agents are reported in
+    /// [`RunResult::elite`].
+    ///
+    /// The elite island is logged as the last one and doesn't take part in the migrations.
+    pub fn elite_island(mut self, prestige: u32) -> Self {
+        assert!(prestige > 0);
+        self.elite_prestige = Some(prestige);
+        self
+    }
+
+    /// Makes the regular islands spatially structured, as in the cellular EMAS: their agents live
+    /// in the cells of the lattice and only reproduce with and fight their neighbours, which
+    /// slows down the takeover by the best agents. The children are placed in the free cells next
+    /// to their parents, who don't reproduce if there is no room. The migrants go to random free
+    /// cells, or wait for the next migration if there are none.
+    pub fn lattice(mut self, lattice: Lattice) -> Self {
+        assert!(lattice.width > 0 && lattice.height > 0);
+        self.lattice = Some(lattice);
+        self
+    }
+
+    /// Records the energy moved by every kind of transfer, which is logged and returned by
+    /// [`System::energy_flows`], and checks after every step and migration that the energy only
+    /// changed by the recorded transfers. A broken invariant means a bug in the system or in a
+    /// custom action.
+    pub fn energy_ledger(mut self, validation: EnergyValidation) -> Self {
+        self.energy_validation = Some(validation);
+        self
+    }
+
+    /// Chooses the opponents of the fighting agents, which are drawn uniformly by default. The
+    /// selection controls the selection pressure and the niching together with the
+    /// [`CombatWinChanceFn`].
+    pub fn partner_selection(mut self, selection: impl PartnerSelectionFn<F::Genome> + 'static) -> Self {
+        self.operators.partner_selection = Some(Box::new(selection));
+        self
+    }
+
+    /// Makes the run reproducible, two systems built with the same configuration and seed
+    /// produce the same logs (apart from the timestamps) and the same solution.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// The number of threads the islands are stepped on, by default one per CPU core. The results
+    /// don't depend on it.
+    pub fn threads(mut self, amount: usize) -> Self {
+        assert!(amount > 0);
+        self.threads = Some(amount);
+        self
+    }
+
+    /// The recombination of the parents' genes, by default the one of the genome, see
+    /// [`Genome::default_recombination`]. It is an instance rather than a type parameter like
+    /// the [`CombatWinChanceFn`], as the operators carry their parameters.
+    pub fn recombination(mut self, recombination: impl RecombinationFn<F::Genome> + 'static) -> Self {
+        self.operators.recombination = Box::new(recombination);
+        self
+    }
+
+    /// The mutation of the children's genes, by default the one of the genome, see
+    /// [`Genome::default_mutation`].
+    pub fn mutation(mut self, mutation: impl MutationFn<F::Genome> + 'static) -> Self {
+        self.operators.mutation = Box::new(mutation);
+        self
+    }
+
+    /// Repairs the genes of every new agent before it is evaluated, none by default.
+    pub fn repair(mut self, repair: impl RepairFn<F::Genome> + 'static) -> Self {
+        self.operators.repair = Some(Box::new(repair));
+        self
+    }
+
+    /// Makes the system memetic: an agent which doesn't reproduce may spend some energy on
+    /// improving its fitness with the local search instead of fighting.
+    pub fn local_search(mut self, search: impl LocalSearchFn<F::Genome> + 'static, mode: LocalSearchMode) -> Self {
+        self.operators.local_search = Some(LocalSearchConf {
+            search: Box::new(search),
+            mode,
+            chance: self.local_search_chance,
+            energy: self.local_search_energy,
+        });
+        self
+    }
+
+    /// The chance that an agent which doesn't reproduce runs the local search, if it has more
+    /// energy than the search costs, 0.1 by default.
+    pub fn local_search_chance(mut self, chance: f64) -> Self {
+        assert!((0.0..=1.0).contains(&chance));
+        self.local_search_chance = chance;
+        self
+    }
+
+    /// The energy an agent spends on a local search, 1 by default.
+    pub fn local_search_energy(mut self, amount: u32) -> Self {
+        self.local_search_energy = amount;
+        self
+    }
+
+    /// Chooses the actions of the agents instead of the [`ReproductionChanceFn`], which is only
+    /// used if no action selection is set. The agents of the elite island always fight. The
+    /// selection may only choose [`Action::LocalSearch`] if the system is built with
+    /// [`Self::local_search`]. As it may choose [`Action::Migrate`], the islands are synchronised
+    /// after every step to deliver the migrants.
+    pub fn action_selection(mut self, selection: impl ActionSelectionFn + 'static) -> Self {
+        self.operators.action_selection = Some(Box::new(selection));
+        self
+    }
+
+    pub fn build(mut self) -> System<F, CF, RF> {
+        if let Some(local_search) = &mut self.operators.local_search {
+            local_search.chance = self.local_search_chance;
+            local_search.energy = self.local_search_energy;
+        }
+
+        let domain = self.fitness.domain();
+        F::Genome::validate_domain(&domain);
+        let problem = Problem {
+            fitness: self.fitness,
+            domain,
+            pareto: self.pareto_reference_point.map(|reference_point| ParetoConf {
+                reference_point: reference_point.into_iter().map(|r| self.direction.minimized(r)).collect(),
+                archive_capacity: self.pareto_archive_capacity,
+            }),
+            direction: self.direction,
+            constraint_handling: self.constraint_handling,
+            equality_tolerance: self.equality_tolerance,
+        };
+
+        let mut rng = match self.seed {
+            Some(seed) => ChaCha8Rng::seed_from_u64(seed),
+            None => ChaCha8Rng::from_entropy(),
+        };
+
+        if let Some(lattice) = &self.lattice {
+            assert!(
+                self.agents_per_island <= lattice.cells(),
+                "The lattice has to have a cell for every agent of an island"
+            );
+        }
+        let grid = self.lattice.map(Grid::new);
+        let mut islands: Vec<_> = (0..self.island_amount)
+            .map(|id| {
+                let island_rng = ChaCha8Rng::from_rng(&mut rng).unwrap();
+                let mut island =
+                    Island::new(self.agents_per_island, self.agent_energy, id, &problem, &self.operators, island_rng);
+                island.lattice = grid.clone();
+                island.scatter_agents();
+                island
+            })
             .collect();
 
-        let logs = vec![
-            "timestamp,historical best,agents amount,energy sum,best living,average fitness,average energy\n".to_string()
-        ];
+        let migration_targets = self.migration_topology.adjacency(self.island_amount, &mut rng);
+
+        if self.elite_prestige.is_some() {
+            let island_rng = ChaCha8Rng::from_rng(&mut rng).unwrap();
+            islands.push(Island::empty(self.island_amount, IslandRole::Elite, &problem, island_rng));
+        }
+        for island in islands.iter_mut() {
+            island.ledger = self.energy_validation.map(EnergyLedger::new);
+        }
+
+        let thread_pool = ThreadPoolBuilder::new()
+            .num_threads(self.threads.unwrap_or(0))
+            .build()
+            .expect("Can't create the thread pool");
 
         System {
             islands,
             steps: self.steps,
-            energy_reproduction_percent: self.energy_passed_on_reproduction,
-            energy_combat: self.energy_combat,
+            energy_policy: self.energy_policy,
             migration_steps: self.migration_steps,
             migrations_best_amount: self.migrations_best_amount,
             migrations_elite_amount: self.migrations_elite_amount,
-            logs,
+            migration_targets,
+            migrant_distribution: self.migrant_distribution,
+            migration_mode: self.migration_mode,
+            elite_prestige: self.elite_prestige,
+            energy_validation: self.energy_validation,
+            termination: TerminationCriteria {
+                target_fitness: self.termination.target_fitness.map(|t| self.direction.minimized(t)),
+                ..self.termination
+            },
+            checkpoint: self.checkpoint,
+            log_sink: self.log_sink,
             log_steps: self.log_steps,
-            f_phantom: PhantomData::default(),
+            problem,
+            operators: self.operators,
+            rng,
+            thread_pool,
+            step: 0,
+            elapsed: Duration::ZERO,
+            last_improvement: 0,
         }
     }
 }
 
 
+
+#[cfg(test)]
+mod tests {
+    use crate::actions::{Action, ActionSelectionFn, AgentState, IslandState};
+    use crate::conf_functions::{DefaultCombatWinChanceFn, ParetoCombatWinChanceFn, ReproductionChanceFn};
+    use crate::energy::{ChildEnergySplit, CombatTransfer, EnergyPolicy, EnergyValidation};
+    use crate::constraints;
+    use crate::constraints::{Constraint, ConstraintHandling};
+    use crate::fitness_functions::{from_fn, Direction, FitnessFn, KnapsackFitness, OneMaxFitness, RastriginFitness};
+    use crate::local_search::{LocalSearchFn, LocalSearchMode, NelderMead};
+    use crate::log_sinks::MemoryLog;
+    use crate::lattice::{Lattice, Neighbourhood};
+    use crate::migration_topologies::{AdjacencyList, MigrationMode, RandomRegular};
+    use crate::partner_selection::{
+        Candidate, EuclideanDistance, GenotypeDistanceFn, NearestGenotypePartner, NeighbourhoodPartner,
+        PartnerSelectionFn, SimilarEnergyPartner, TournamentPartner, UniformPartner,
+    };
+    use crate::pareto::dominates;
+    use crate::termination::TerminationReason;
+    use crate::tsp::TspFitness;
+    use rand::{Rng, RngCore};
+    use std::collections::BTreeSet;
+    use std::f64::consts::PI;
+    use std::sync::atomic::{self, AtomicU64};
+    use std::sync::Arc;
+    use crate::{AgentId, ReproductionChance, SystemBuilder};
+
+    /// The builder the tests on the Rastrigin function start from.
+    fn rastrigin(island_amount: usize, agents_per_island: usize) -> SystemBuilder<RastriginFitness<5>> {
+        SystemBuilder::new()
+            .island_amount(island_amount)
+            .agents_per_island(agents_per_island)
+    }
+
+    #[test]
+    fn same_seed_same_run_test() {
+        let run = |threads| {
+            let log = MemoryLog::new();
+            let mut system = rastrigin(3, 20)
+                .steps(200)
+                .log_steps(10)
+                .seed(42)
+                .threads(threads)
+                .log_sink(log.clone())
+                .build();
+            let best_sol = system.run().best_sol;
+            let mut records = log.records();
+            for record in &mut records {
+                record.timestamp = 0.0;
+            }
+            (records, best_sol)
+        };
+
+        assert_eq!(run(1), run(1));
+        assert_eq!(run(1), run(3));
+    }
+
+    #[test]
+    fn max_evaluations_test() {
+        let builder = || {
+            rastrigin(3, 20)
+                .seed(12)
+        };
+        let result = builder().max_evaluations(1_000).build().run();
+        assert_eq!(result.termination, TerminationReason::Evaluations);
+        assert!(result.evaluations >= 1_000);
+        // The budget wasn't spent a step earlier.
+        let shorter = builder().steps(result.steps - 1).build().run();
+        assert!(shorter.evaluations < 1_000);
+    }
+
+    #[test]
+    fn termination_step_test() {
+        // Synchronising the islands after every step doesn't change the run, so the run stops at
+        // the same step and evaluation as with the logging after every step.
+        let run = |builder: SystemBuilder<RastriginFitness<5>>| {
+            let result = builder.island_amount(3).agents_per_island(20).seed(14).build().run();
+            (result.termination, result.steps, result.evaluations)
+        };
+        let criteria = [
+            |b: SystemBuilder<RastriginFitness<5>>| b.target_fitness(15.0),
+            |b: SystemBuilder<RastriginFitness<5>>| b.stagnation_steps(30),
+            |b: SystemBuilder<RastriginFitness<5>>| {
+                // Every agent reproduces in every step, until the parents and the children die.
+                b.agent_energy(30).energy_policy(EnergyPolicy {
+                    death_threshold: 10,
+                    reproduction_threshold: 0,
+                    reproduction_transfer: 0.5,
+                    ..EnergyPolicy::default()
+                })
+            },
+        ];
+        for (criterion, reason) in criteria.into_iter().zip([
+            TerminationReason::TargetFitness,
+            TerminationReason::Stagnation,
+            TerminationReason::Extinction,
+        ]) {
+            let (termination, steps, evaluations) = run(criterion(SystemBuilder::new()));
+            assert_eq!(termination, reason);
+            assert!(steps > 1);
+            assert_eq!((termination, steps, evaluations), run(criterion(SystemBuilder::new().log_steps(1))));
+        }
+    }
+
+    #[test]
+    fn zero_steps_test() {
+        let log = MemoryLog::new();
+        let result = rastrigin(3, 20)
+            .steps(0)
+            .seed(13)
+            .log_sink(log.clone())
+            .build()
+            .run();
+        assert_eq!(result.termination, TerminationReason::Steps);
+        assert_eq!(result.steps, 0);
+        assert_eq!(result.evaluations, 3 * 20);
+        assert!(log.records().is_empty());
+    }
+
+    #[test]
+    fn checkpoint_resume_test() {
+        let path = std::env::temp_dir().join(format!("emas_rs_checkpoint_test_{}.json", std::process::id()));
+        let builder = |log: &MemoryLog| {
+            rastrigin(3, 20)
+                .steps(200)
+                .log_steps(10)
+                .migration_steps(25)
+                .seed(7)
+                .log_sink(log.clone())
+        };
+        let without_timestamps = |log: &MemoryLog| {
+            let mut records = log.records();
+            for record in &mut records {
+                record.timestamp = 0.0;
+            }
+            records
+        };
+
+        let full_log = MemoryLog::new();
+        let full = builder(&full_log).checkpoint(&path, 120).build().run();
+        assert!(!path.with_extension("json.tmp").exists());
+
+        let resumed_log = MemoryLog::new();
+        let mut system = builder(&resumed_log).build();
+        system.load_checkpoint(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let resumed = system.run();
+
+        assert_eq!(full.best_sol, resumed.best_sol);
+        assert_eq!(full.evaluations, resumed.evaluations);
+        let full_records: Vec<_> = without_timestamps(&full_log)
+            .into_iter()
+            .filter(|r| r.step >= 120)
+            .collect();
+        assert_eq!(full_records, without_timestamps(&resumed_log));
+    }
+
+    #[test]
+    fn checkpoint_topology_test() {
+        let path = std::env::temp_dir().join(format!("emas_rs_checkpoint_topology_test_{}.json", std::process::id()));
+        // Unseeded, so the two systems draw different topologies.
+        let builder = || {
+            rastrigin(8, 10)
+                .steps(20)
+                .migration_topology(RandomRegular { degree: 3 })
+        };
+        let mut saved = builder().checkpoint(&path, 10).build();
+        saved.run();
+        let mut resumed = builder().build();
+        resumed.load_checkpoint(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(saved.migration_targets, resumed.migration_targets);
+
+        // An elite island in place of a regular one keeps the amount of the islands the same.
+        saved.save_checkpoint(&path).unwrap();
+        let mut elite = rastrigin(7, 10).elite_island(5).build();
+        let error = elite.load_checkpoint(&path).unwrap_err();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    }
+
+    /// The sphere function, which can't be evaluated where the first coordinate is positive.
+    struct HalfInfiniteFitness;
+
+    impl FitnessFn for HalfInfiniteFitness {
+        type Genome = Vec<f64>;
+
+        fn domain(&self) -> Vec<(f64, f64)> {
+            vec![(-5.0, 5.0); 2]
+        }
+
+        fn call(&self, args: &Vec<f64>) -> f64 {
+            if args[0] > 0.0 {
+                f64::INFINITY
+            } else {
+                args.iter().map(|x| x.powi(2)).sum()
+            }
+        }
+    }
+
+    #[test]
+    fn checkpoint_infinite_fitness_test() {
+        let path = std::env::temp_dir().join(format!("emas_rs_checkpoint_infinite_test_{}.json", std::process::id()));
+        let builder = || {
+            SystemBuilder::<HalfInfiniteFitness>::with_fitness(HalfInfiniteFitness)
+                .island_amount(2)
+                .agents_per_island(20)
+                .steps(10)
+                .seed(5)
+        };
+        let mut saved = builder().build();
+        saved.run();
+        saved.save_checkpoint(&path).unwrap();
+        let mut resumed = builder().build();
+        resumed.load_checkpoint(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let saved: Vec<_> = saved.islands.iter().flat_map(|i| i.agents.values().map(|a| a.fitness)).collect();
+        let resumed: Vec<_> = resumed.islands.iter().flat_map(|i| i.agents.values().map(|a| a.fitness)).collect();
+        assert!(saved.contains(&f64::INFINITY));
+        assert_eq!(saved, resumed);
+    }
+
+    /// Schaffer's problem, whose Pareto optimal solutions are `0 <= x <= 2`.
+    struct SchafferFitness;
+
+    impl FitnessFn for SchafferFitness {
+        type Genome = Vec<f64>;
+
+        fn domain(&self) -> Vec<(f64, f64)> {
+            vec![(-10.0, 10.0)]
+        }
+
+        fn call(&self, args: &Vec<f64>) -> f64 {
+            self.objectives(args).iter().sum()
+        }
+
+        fn objectives(&self, args: &Vec<f64>) -> Vec<f64> {
+            vec![args[0].powi(2), (args[0] - 2.0).powi(2)]
+        }
+    }
+
+    #[test]
+    fn pareto_front_test() {
+        let log = MemoryLog::new();
+        let result = SystemBuilder::<SchafferFitness, ParetoCombatWinChanceFn>::with_fitness(SchafferFitness)
+            .island_amount(3)
+            .agents_per_island(30)
+            .steps(300)
+            .seed(3)
+            .pareto(vec![5.0, 5.0])
+            .pareto_archive_capacity(20)
+            .log_sink(log.clone())
+            .build()
+            .run();
+
+        let front = result.pareto_front;
+        assert!(!front.is_empty() && front.len() <= 20);
+        // The Pareto set of the Schaffer function is `0 <= x <= 2`, the front only gets close to its ends.
+        assert!(front.iter().all(|s| (-0.01..=2.01).contains(&s.genes[0])), "{:?}", front);
+        for s1 in &front {
+            assert!(front.iter().all(|s2| !dominates(&s2.objectives, &s1.objectives)));
+        }
+        let records = log.records();
+        let hypervolume = records.last().unwrap().hypervolume.unwrap();
+        assert!(hypervolume > 0.0 && hypervolume >= records[0].hypervolume.unwrap());
+    }
+
+    /// Schaffer's problem, which can only be evaluated in batches.
+    #[derive(Default)]
+    struct BatchSchafferFitness {
+        evaluations: AtomicU64,
+    }
+
+    impl FitnessFn for BatchSchafferFitness {
+        type Genome = Vec<f64>;
+
+        fn domain(&self) -> Vec<(f64, f64)> {
+            SchafferFitness.domain()
+        }
+
+        fn call(&self, _args: &Vec<f64>) -> f64 {
+            panic!("The genotypes have to be evaluated in batches")
+        }
+
+        fn call_batch(&self, _args: &[&Vec<f64>]) -> Vec<f64> {
+            panic!("The fitness has to be derived from the objectives")
+        }
+
+        fn objectives(&self, _args: &Vec<f64>) -> Vec<f64> {
+            panic!("The objectives have to be evaluated in batches")
+        }
+
+        fn objectives_batch(&self, args: &[&Vec<f64>]) -> Vec<Vec<f64>> {
+            self.evaluations.fetch_add(args.len() as u64, atomic::Ordering::Relaxed);
+            args.iter().map(|genes| SchafferFitness.objectives(genes)).collect()
+        }
+
+        fn constraints(&self, _args: &Vec<f64>) -> Vec<Constraint> {
+            panic!("The constraints have to be evaluated in batches")
+        }
+
+        fn constraints_batch(&self, args: &[&Vec<f64>]) -> Vec<Vec<Constraint>> {
+            vec![Vec::new(); args.len()]
+        }
+    }
+
+    #[test]
+    fn pareto_batch_evaluation_test() {
+        let fitness = Arc::new(BatchSchafferFitness::default());
+        let result = SystemBuilder::<_, ParetoCombatWinChanceFn>::with_fitness(fitness.clone())
+            .island_amount(3)
+            .agents_per_island(20)
+            .steps(100)
+            .seed(4)
+            .pareto(vec![5.0, 5.0])
+            .build()
+            .run();
+        assert_eq!(fitness.evaluations.load(atomic::Ordering::Relaxed), result.evaluations);
+        assert!(!result.pareto_front.is_empty());
+        assert_eq!(result.best_fitness, SchafferFitness.objectives(&result.best_sol).iter().sum::<f64>());
+    }
+
+    #[test]
+    fn one_max_test() {
+        let result = SystemBuilder::<OneMaxFitness<32>>::new()
+            .island_amount(3)
+            .agents_per_island(30)
+            .steps(2_000)
+            .seed(11)
+            .target_fitness(0.0)
+            .build()
+            .run();
+        assert_eq!(result.best_fitness, 0.0);
+        assert_eq!(result.best_sol, [true; 32]);
+    }
+
+    #[test]
+    fn knapsack_test() {
+        // The P01 instance, whose optimal packing is worth 309.
+        let knapsack = KnapsackFitness {
+            values: vec![92.0, 57.0, 49.0, 68.0, 60.0, 43.0, 67.0, 84.0, 87.0, 72.0],
+            weights: vec![23.0, 31.0, 29.0, 44.0, 53.0, 38.0, 63.0, 85.0, 89.0, 82.0],
+            capacity: 165.0,
+        };
+        let result = SystemBuilder::<KnapsackFitness>::with_fitness(knapsack)
+            .island_amount(3)
+            .agents_per_island(30)
+            .steps(2_000)
+            .seed(5)
+            .target_fitness(-309.0)
+            .build()
+            .run();
+        assert_eq!(result.best_fitness, -309.0);
+    }
+
+    #[test]
+    fn tsp_test() {
+        // On a circle the shortest tour visits the cities in the order of their angles.
+        let cities: Vec<_> = [3, 7, 0, 5, 9, 1, 4, 8, 2, 6]
+            .iter()
+            .map(|&i| (2.0 * PI * i as f64 / 10.0).sin_cos())
+            .collect();
+        let perimeter = 10.0 * 2.0 * (PI / 10.0).sin();
+        let result = SystemBuilder::<TspFitness>::with_fitness(TspFitness::from_coordinates(&cities))
+            .island_amount(3)
+            .agents_per_island(30)
+            .steps(2_000)
+            .seed(1)
+            .target_fitness(perimeter + 1e-9)
+            .build()
+            .run();
+        assert!(result.best_sol.is_valid());
+        assert!((result.best_fitness - perimeter).abs() < 1e-9);
+    }
+
+    /// The sphere function with the constraint `x + y >= 1`, minimized at `(0.5, 0.5)`.
+    struct ConstrainedSphereFitness;
+
+    impl FitnessFn for ConstrainedSphereFitness {
+        type Genome = [f64; 2];
+
+        fn domain(&self) -> [(f64, f64); 2] {
+            [(-2.0, 2.0); 2]
+        }
+
+        fn call(&self, args: &[f64; 2]) -> f64 {
+            args[0].powi(2) + args[1].powi(2)
+        }
+
+        fn constraints(&self, args: &[f64; 2]) -> Vec<Constraint> {
+            vec![Constraint::Inequality(1.0 - args[0] - args[1])]
+        }
+    }
+
+    #[test]
+    fn constraint_handling_test() {
+        let handlings = [
+            ConstraintHandling::FeasibilityRules,
+            ConstraintHandling::StaticPenalty { coefficient: 100.0 },
+            ConstraintHandling::DynamicPenalty { c: 0.5, alpha: 2.0 },
+        ];
+        for handling in handlings {
+            let log = MemoryLog::new();
+            let result = SystemBuilder::<ConstrainedSphereFitness>::with_fitness(ConstrainedSphereFitness)
+                .island_amount(3)
+                .agents_per_island(30)
+                .steps(1_000)
+                .seed(2)
+                .constraint_handling(handling)
+                // Logs the last step too, so the last record matches the result.
+                .log_steps(999)
+                .log_sink(log.clone())
+                .build()
+                .run();
+            let best = result.best_feasible_sol.unwrap();
+            assert!(best[0] + best[1] >= 1.0);
+            assert_eq!(result.best_feasible_fitness, Some(ConstrainedSphereFitness.call(&best)));
+            assert!(result.best_feasible_fitness.unwrap() < 0.51, "{:?}", handling);
+            let record = log.records().pop().unwrap();
+            assert!(record.feasibility_ratio.unwrap() > 0.5, "{:?}", handling);
+            assert_eq!(record.step, 999);
+            assert_eq!(record.best_feasible, result.best_feasible_fitness);
+        }
+
+        // Shifting the infeasible points onto the line keeps every agent feasible.
+        let log = MemoryLog::new();
+        let result = SystemBuilder::<ConstrainedSphereFitness>::with_fitness(ConstrainedSphereFitness)
+            .island_amount(3)
+            .agents_per_island(30)
+            .steps(300)
+            .seed(2)
+            .repair(constraints::from_fn(|genes: &mut [f64; 2]| {
+                let shift = (1.0 + 1e-9 - genes[0] - genes[1]).max(0.0) / 2.0;
+                genes[0] += shift;
+                genes[1] += shift;
+            }))
+            .log_sink(log.clone())
+            .build()
+            .run();
+        assert!(log.records().iter().all(|r| r.feasibility_ratio == Some(1.0)));
+        assert_eq!(result.best_feasible_fitness, Some(result.best_fitness));
+    }
+
+    #[test]
+    fn maximize_test() {
+        let ones = from_fn((), |genes: &[bool; 32]| genes.iter().filter(|&&bit| bit).count() as f64);
+        let log = MemoryLog::new();
+        let result = SystemBuilder::<_>::with_fitness(ones)
+            .island_amount(3)
+            .agents_per_island(30)
+            .steps(2_000)
+            .seed(11)
+            .direction(Direction::Maximize)
+            .target_fitness(32.0)
+            .log_sink(log.clone())
+            .build()
+            .run();
+        assert_eq!(result.termination, TerminationReason::TargetFitness);
+        assert_eq!(result.best_fitness, 32.0);
+        assert_eq!(result.best_sol, [true; 32]);
+
+        let records = log.records();
+        assert!(records.windows(2).all(|r| r[0].historical_best <= r[1].historical_best));
+        for record in records {
+            let best_living = record.best_living.unwrap();
+            assert!(best_living >= record.average_fitness.unwrap());
+            assert!(record.historical_best >= best_living);
+        }
+    }
+
+    /// The Rastrigin function, which can only be evaluated in batches, with a constraint which
+    /// is always satisfied.
+    #[derive(Default)]
+    struct BatchFitness {
+        batches: AtomicU64,
+        constraint_batches: AtomicU64,
+        evaluations: AtomicU64,
+    }
+
+    impl FitnessFn for BatchFitness {
+        type Genome = [f64; 5];
+
+        fn domain(&self) -> [(f64, f64); 5] {
+            RastriginFitness::<5>::default().domain()
+        }
+
+        fn call(&self, _args: &[f64; 5]) -> f64 {
+            panic!("The genotypes have to be evaluated in batches")
+        }
+
+        fn call_batch(&self, args: &[&[f64; 5]]) -> Vec<f64> {
+            self.batches.fetch_add(1, atomic::Ordering::Relaxed);
+            self.evaluations.fetch_add(args.len() as u64, atomic::Ordering::Relaxed);
+            args.iter().map(|genes| RastriginFitness::<5>::default().call(genes)).collect()
+        }
+
+        fn constraints(&self, _args: &[f64; 5]) -> Vec<Constraint> {
+            panic!("The constraints have to be evaluated in batches")
+        }
+
+        fn constraints_batch(&self, args: &[&[f64; 5]]) -> Vec<Vec<Constraint>> {
+            self.constraint_batches.fetch_add(1, atomic::Ordering::Relaxed);
+            args.iter().map(|genes| vec![Constraint::Inequality(genes[0] - 10.0)]).collect()
+        }
+    }
+
+    #[test]
+    fn batch_evaluation_test() {
+        let fitness = Arc::new(BatchFitness::default());
+        let result = SystemBuilder::<_>::with_fitness(fitness.clone())
+            .island_amount(3)
+            .agents_per_island(20)
+            .steps(100)
+            .seed(4)
+            .build()
+            .run();
+        assert_eq!(fitness.evaluations.load(atomic::Ordering::Relaxed), result.evaluations);
+        // One batch per island for the initial agents and at most one per island step.
+        let batches = fitness.batches.load(atomic::Ordering::Relaxed);
+        assert!(batches <= 3 + 3 * 100);
+        assert!(result.evaluations > 2 * batches);
+        // The constraints of every batch are evaluated in a batch too.
+        assert_eq!(fitness.constraint_batches.load(atomic::Ordering::Relaxed), batches);
+    }
+
+    #[test]
+    fn elite_island_test() {
+        let log = MemoryLog::new();
+        let result = rastrigin(3, 30)
+            .steps(500)
+            .seed(6)
+            .elite_island(5)
+            .log_steps(1)
+            .log_sink(log.clone())
+            .build()
+            .run();
+
+        let elite = result.elite;
+        assert!(!elite.is_empty());
+        assert!(elite.iter().all(|s| s.prestige >= 5));
+        assert!(elite.windows(2).all(|s| s[0].fitness <= s[1].fitness));
+        assert_eq!(elite[0].fitness, RastriginFitness::<5>::default().call(&elite[0].genes));
+        let records = log.records();
+        let last = records.last().unwrap();
+        assert_eq!(last.islands.len(), 4);
+        assert!(last.islands[3].agents_amount > 0);
+        // No agents are born on the elite island, they only come in at the migrations.
+        for r in records.windows(2).filter(|r| !r[1].step.is_multiple_of(50)) {
+            assert!(r[1].islands[3].agents_amount <= r[0].islands[3].agents_amount, "step {}", r[1].step);
+        }
+    }
+
+    /// Checks that the search gets the fitness of the genes it starts from.
+    #[derive(Debug)]
+    struct CheckedSearch<S>(S);
+
+    impl<G: crate::genome::Genome, S: LocalSearchFn<G>> LocalSearchFn<G> for CheckedSearch<S> {
+        fn call(
+            &self,
+            genes: &G,
+            fitness: f64,
+            evaluate: &mut dyn FnMut(&G) -> f64,
+            domain: &G::Domain,
+            rng: &mut dyn RngCore,
+        ) -> (G, f64) {
+            assert_eq!(evaluate(genes), fitness);
+            self.0.call(genes, fitness, evaluate, domain, rng)
+        }
+    }
+
+    #[test]
+    fn memetic_test() {
+        let run = |mode| {
+            let log = MemoryLog::new();
+            let result = rastrigin(3, 20)
+                .steps(300)
+                .seed(8)
+                .local_search(NelderMead::default(), mode)
+                .local_search_chance(0.2)
+                .log_sink(log.clone())
+                .build()
+                .run();
+            (result, log.records().pop().unwrap())
+        };
+
+        for mode in [LocalSearchMode::Lamarckian, LocalSearchMode::Baldwinian] {
+            let (result, record) = run(mode);
+            assert!(record.local_search_evaluations > 0);
+            assert!(result.evaluations > record.local_search_evaluations);
+            assert_eq!(RastriginFitness::<5>::default().call(&result.best_sol), result.best_fitness);
+        }
+
+        // Lamarckian agents take the improved genes, Baldwinian ones keep theirs.
+        for mode in [LocalSearchMode::Lamarckian, LocalSearchMode::Baldwinian] {
+            let mut system = rastrigin(1, 20)
+                .seed(8)
+                .local_search(CheckedSearch(NelderMead::default()), mode)
+                .build();
+            let island = &mut system.islands[0];
+            let before = island.agents.clone();
+            let ids: Vec<_> = before.keys().copied().collect();
+            let local_search = system.operators.local_search.as_ref().unwrap();
+            // The second search starts from the same genes and fitness again if the agents are
+            // Baldwinian.
+            island.local_searches(ids.clone(), &system.problem, local_search, 0);
+            island.local_searches(ids, &system.problem, local_search, 1);
+
+            let mut improved = 0;
+            for (id, agent) in &island.agents {
+                let old = &before[id];
+                assert_eq!(agent.fitness, RastriginFitness::<5>::default().call(&agent.genes));
+                match mode {
+                    LocalSearchMode::Lamarckian => {
+                        assert!(agent.learned.is_none());
+                        assert!(agent.fitness <= old.fitness);
+                        if agent.fitness < old.fitness {
+                            assert_ne!(agent.genes, old.genes);
+                            improved += 1;
+                        }
+                    }
+                    LocalSearchMode::Baldwinian => {
+                        assert_eq!(agent.genes, old.genes);
+                        assert_eq!(agent.fitness, old.fitness);
+                        if let Some(learned) = &agent.learned {
+                            assert!(learned.fitness < old.fitness);
+                            improved += 1;
+                        }
+                    }
+                }
+            }
+            assert!(improved > 10);
+        }
+    }
+
+    /// Reproduces above an energy threshold, rests below another one and otherwise fights, apart
+    /// from the old agents which emigrate and the custom action which takes a unit of energy.
+    #[derive(Debug, Default)]
+    struct ThresholdSelection {
+        custom_actions: Arc<AtomicU64>,
+    }
+
+    impl ActionSelectionFn for ThresholdSelection {
+        fn call(&self, agent: &AgentState, island: &IslandState, rng: &mut dyn RngCore) -> Action {
+            assert!(agent.age <= island.step);
+            assert!(island.best_fitness <= agent.fitness);
+            if agent.energy >= 30 {
+                Action::Reproduce
+            } else if agent.energy < 3 {
+                Action::Rest
+            } else if agent.age > 20 && rng.gen::<f64>() < 0.05 {
+                Action::Migrate
+            } else if rng.gen::<f64>() < 0.05 {
+                Action::Custom(7)
+            } else {
+                Action::Combat
+            }
+        }
+
+        fn custom(&self, action: u32, _agent: &AgentState, energy: &mut u32, _rng: &mut dyn RngCore) {
+            assert_eq!(action, 7);
+            *energy -= 1;
+            self.custom_actions.fetch_add(1, atomic::Ordering::Relaxed);
+        }
+    }
+
+    #[test]
+    fn action_selection_test() {
+        let run = |seed| {
+            let log = MemoryLog::new();
+            let selection = ThresholdSelection::default();
+            let custom_actions = selection.custom_actions.clone();
+            let mut system = rastrigin(3, 20)
+                .steps(300)
+                .seed(seed)
+                .action_selection(selection)
+                .energy_ledger(EnergyValidation::Panic)
+                .log_sink(log.clone())
+                .build();
+            let best_sol = system.run().best_sol;
+
+            // Every custom action took exactly a unit of energy.
+            let flows = system.energy_flows();
+            assert!(flows.custom_lost > 0);
+            assert_eq!(flows.custom_lost, custom_actions.load(atomic::Ordering::Relaxed));
+            assert_eq!(flows.custom_gained, 0);
+            (best_sol, log.records().into_iter().map(|r| r.islands).collect::<Vec<_>>())
+        };
+        let (best_sol, islands) = run(9);
+        assert_eq!((best_sol, islands.clone()), run(9));
+        assert!(islands.last().unwrap().iter().all(|i| i.agents_amount > 0));
+    }
+
+    #[derive(Debug)]
+    struct LocalSearchSelection;
+
+    impl ActionSelectionFn for LocalSearchSelection {
+        fn call(&self, _agent: &AgentState, _island: &IslandState, _rng: &mut dyn RngCore) -> Action {
+            Action::LocalSearch
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Action::LocalSearch needs a local search")]
+    fn local_search_action_without_local_search_test() {
+        rastrigin(1, 10)
+            .steps(10)
+            .threads(1)
+            .action_selection(LocalSearchSelection)
+            .build()
+            .run();
+    }
+
+    #[test]
+    fn agent_initiated_migration_test() {
+        let mut system = rastrigin(3, 20)
+            .steps(50)
+            .migration_steps(1_000)
+            .seed(10)
+            .migration_topology(AdjacencyList(vec![vec![1], vec![], vec![]]))
+            .migration_mode(MigrationMode::AgentInitiated {
+                energy_threshold: 15,
+                chance: 0.2,
+            })
+            .build();
+        system.run();
+
+        // The migrants can only go from the first island to the second one, the emigrants of the
+        // other islands come back. They arrive at the end of the step they left in, without
+        // waiting for the migrations of the whole islands.
+        assert!(system.islands[0].agents.keys().all(|id| id.0 == 0));
+        assert!(system.islands[1].agents.keys().any(|id| id.0 == 0));
+        assert!(system.islands[2].agents.keys().all(|id| id.0 == 2));
+        assert!(system.islands.iter().all(|island| island.migration_queue.is_empty()));
+    }
+
+    #[test]
+    fn energy_ledger_test() {
+        let log = MemoryLog::new();
+        let mut system = rastrigin(3, 20)
+            .agent_energy(30)
+            .steps(100)
+            .migration_steps(10)
+            .log_steps(50)
+            .seed(3)
+            .local_search(NelderMead::default(), LocalSearchMode::Lamarckian)
+            .migration_mode(MigrationMode::AgentInitiated {
+                energy_threshold: 15,
+                chance: 0.2,
+            })
+            .elite_island(3)
+            .energy_ledger(EnergyValidation::Panic)
+            .log_sink(log.clone())
+            .build();
+        system.run();
+
+        let flows = system.energy_flows();
+        assert!(flows.reproduction > 0 && flows.combat > 0);
+        assert!(flows.migration > 0 && flows.local_search > 0);
+        let logged = log.records().last().unwrap().energy_flows.unwrap();
+        assert!(logged.combat > 0 && logged.combat <= flows.combat);
+    }
+
+    /// Always reproduces, checking that it is only asked about the agents above the threshold.
+    struct EagerReproductionChanceFn;
+
+    impl ReproductionChanceFn for EagerReproductionChanceFn {
+        fn call(_energy: u32) -> ReproductionChance {
+            ReproductionChance(1.0)
+        }
+
+        fn with_threshold(energy: u32, threshold: u32) -> ReproductionChance {
+            assert!(energy >= threshold);
+            Self::call(energy)
+        }
+    }
+
+    #[test]
+    fn energy_policy_test() {
+        // The agents below the reproduction threshold fight instead of reproducing.
+        type Builder = SystemBuilder<RastriginFitness<5>, DefaultCombatWinChanceFn, EagerReproductionChanceFn>;
+        let mut system = Builder::new()
+            .island_amount(2)
+            .agents_per_island(30)
+            .agent_energy(30)
+            .steps(100)
+            .seed(4)
+            .energy_policy(EnergyPolicy {
+                death_threshold: 5,
+                reproduction_threshold: 20,
+                reproduction_transfer: 0.5,
+                combat_transfer: CombatTransfer::Proportional(0.2),
+                child_split: ChildEnergySplit::PerParent,
+            })
+            .energy_ledger(EnergyValidation::Panic)
+            .build();
+        system.run();
+
+        let flows = system.energy_flows();
+        assert!(flows.death > 0 && flows.reproduction > 0 && flows.combat > 0);
+        assert!(system.islands.iter().flat_map(|i| i.agents.values()).all(|a| a.energy > 5));
+
+        // The loser gives away a fifth of its energy.
+        let mut agents = system.islands[0].agents.values().cloned();
+        let (mut a1, mut a2) = (agents.next().unwrap(), agents.next().unwrap());
+        (a1.energy, a2.energy) = (50, 31);
+        let transferred = a1.combat(&mut a2, system.energy_policy.combat_transfer, 1.0, &mut system.rng);
+        assert_eq!((transferred, a1.energy, a2.energy), (6, 56, 25));
+
+        // Every child gets the half of the energy of its own parent.
+        (a1.energy, a2.energy) = (40, 21);
+        let ids = (AgentId(0, 1000), AgentId(0, 1001));
+        let children =
+            a1.reproduce(&mut a2, &system.energy_policy, &system.problem, &system.operators, ids, &mut system.rng);
+        assert_eq!([children[0].energy, children[1].energy], [20, 10]);
+        assert_eq!((a1.energy, a2.energy), (20, 11));
+    }
+
+    #[test]
+    fn extinction_test() {
+        let log = MemoryLog::new();
+        let result = rastrigin(2, 20)
+            .seed(4)
+            .energy_policy(EnergyPolicy {
+                death_threshold: 50,
+                ..EnergyPolicy::default()
+            })
+            .log_sink(log.clone())
+            .build()
+            .run();
+
+        assert_eq!(result.termination, TerminationReason::Extinction);
+        assert_eq!(result.steps, 1);
+        let record = log.records().pop().unwrap();
+        assert_eq!(record.agents_amount, 0);
+        assert_eq!(record.best_living, None);
+        assert_eq!(record.average_energy, None);
+        assert!(record.historical_best.is_finite());
+    }
+
+    /// Checks every opponent chosen by the wrapped selection and counts the combats.
+    struct CheckedPartner<S, C> {
+        selection: S,
+        check: C,
+        combats: Arc<AtomicU64>,
+    }
+
+    impl<S, C> std::fmt::Debug for CheckedPartner<S, C> {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.debug_struct("CheckedPartner").finish_non_exhaustive()
+        }
+    }
+
+    impl<S, C> PartnerSelectionFn<[f64; 5]> for CheckedPartner<S, C>
+        where
+            S: PartnerSelectionFn<[f64; 5]>,
+            C: Fn(&Candidate<[f64; 5]>, &[Candidate<[f64; 5]>], usize) + Send + Sync
+    {
+        fn call(
+            &self,
+            agent: &Candidate<[f64; 5]>,
+            candidates: &[Candidate<[f64; 5]>],
+            rng: &mut dyn RngCore,
+        ) -> Option<usize> {
+            let choice = self.selection.call(agent, candidates, rng);
+            if let Some(i) = choice {
+                (self.check)(agent, candidates, i);
+                self.combats.fetch_add(1, atomic::Ordering::Relaxed);
+            }
+            choice
+        }
+    }
+
+    #[test]
+    fn partner_selection_test() {
+        /// Returns the amount of the combats, after checking every chosen opponent.
+        fn run<S, C>(selection: S, check: C) -> u64
+            where
+                S: PartnerSelectionFn<[f64; 5]> + 'static,
+                C: Fn(&Candidate<[f64; 5]>, &[Candidate<[f64; 5]>], usize) + Send + Sync + 'static
+        {
+            let combats = Arc::new(AtomicU64::new(0));
+            let mut system = rastrigin(2, 20)
+                .steps(100)
+                .seed(5)
+                .energy_ledger(EnergyValidation::Panic)
+                .partner_selection(CheckedPartner {
+                    selection,
+                    check,
+                    combats: combats.clone(),
+                })
+                .build();
+            system.run();
+            assert!(system.energy_flows().combat > 0);
+            combats.load(atomic::Ordering::Relaxed)
+        }
+        let any = |_: &Candidate<[f64; 5]>, _: &[Candidate<[f64; 5]>], _: usize| {};
+
+        run(NearestGenotypePartner { distance: EuclideanDistance }, any);
+        run(SimilarEnergyPartner, any);
+        // The opponent is the best of the tournament, so it is at least as good as the other
+        // drawn candidates.
+        run(TournamentPartner { size: 3 }, |_, candidates, i| {
+            let not_better = candidates.iter().filter(|c| c.fitness >= candidates[i].fitness).count();
+            assert!(not_better >= candidates.len().min(3));
+        });
+        // The opponents are only drawn from the neighbourhood of the agent, so fewer agents fight.
+        let radius = 4.0;
+        let neighbourhood = NeighbourhoodPartner {
+            radius,
+            distance: EuclideanDistance,
+        };
+        let neighbourhood_combats = run(neighbourhood, move |agent, candidates, i| {
+            assert!(EuclideanDistance.call(agent.genes, candidates[i].genes) <= radius);
+        });
+        assert!(neighbourhood_combats < run(UniformPartner, any));
+    }
+
+    #[test]
+    fn lattice_test() {
+        let log = MemoryLog::new();
+        let lattice = Lattice {
+            width: 8,
+            height: 6,
+            neighbourhood: Neighbourhood::Moore,
+        };
+        let mut system = rastrigin(3, 40)
+            .agent_energy(30)
+            .steps(200)
+            .seed(6)
+            .lattice(lattice)
+            .energy_ledger(EnergyValidation::Panic)
+            .log_sink(log.clone())
+            .build();
+        system.run();
+
+        assert!(system.energy_flows().reproduction > 0);
+        for island in &system.islands {
+            let cells: BTreeSet<_> = island.agents.values().map(|a| a.cell.unwrap()).collect();
+            assert_eq!(cells.len(), island.agents.len());
+            assert!(cells.iter().all(|&cell| cell < lattice.cells()));
+        }
+        let record = log.records().pop().unwrap().islands.remove(0);
+        assert!(record.occupancy.unwrap() <= 1.0);
+        assert!(record.neighbour_fitness_difference.is_some());
+
+        // On a checkerboard no agent has a neighbour, so none of them fights or reproduces.
+        let mut system = rastrigin(1, 32)
+            .seed(6)
+            .lattice(Lattice {
+                width: 8,
+                height: 8,
+                neighbourhood: Neighbourhood::VonNeumann,
+            })
+            .build();
+        let island = &mut system.islands[0];
+        let black = (0..64).filter(|cell| (cell % 8 + cell / 8) % 2 == 0);
+        for (agent, cell) in island.agents.values_mut().zip(black) {
+            agent.cell = Some(cell);
+        }
+        let before = island.agents.clone();
+        let ids: Vec<_> = before.keys().copied().collect();
+        island.reproductions(ids.clone(), &system.energy_policy, &system.problem, &system.operators, 0);
+        island.combats(ids.clone(), system.energy_policy.combat_transfer, &system.problem, &system.operators, 0);
+        assert_eq!(island.agents.len(), before.len());
+        assert!(island.agents.values().all(|a| a.energy == before[&a.id].energy));
+
+        // Once the second agent moves next to the first one, they reproduce and their children
+        // are placed next to them.
+        assert_eq!(island.agents[&ids[0]].cell, Some(0));
+        island.agents.get_mut(&ids[1]).unwrap().cell = Some(1);
+        let occupied = island.occupied_cells();
+        island.reproductions(ids[..2].to_vec(), &system.energy_policy, &system.problem, &system.operators, 0);
+        let grid = island.lattice.as_ref().unwrap();
+        let children: Vec<_> = island.agents.values().filter(|a| !before.contains_key(&a.id)).collect();
+        assert_eq!(children.len(), 2);
+        for child in children {
+            let cell = child.cell.unwrap();
+            assert!(!occupied.contains(&cell));
+            assert!(grid.are_neighbours(0, cell) || grid.are_neighbours(1, cell));
+        }
+    }
+
+    #[test]
+    fn lattice_local_search_test() {
+        let lattice = Lattice {
+            width: 6,
+            height: 6,
+            neighbourhood: Neighbourhood::VonNeumann,
+        };
+        let log = MemoryLog::new();
+        let mut system = rastrigin(2, 25)
+            .agent_energy(30)
+            .steps(100)
+            .log_steps(10)
+            .seed(7)
+            .lattice(lattice)
+            .local_search(NelderMead::default(), LocalSearchMode::Lamarckian)
+            .local_search_chance(0.3)
+            .log_sink(log.clone())
+            .build();
+        system.run();
+
+        // The improved agents stay in their cells.
+        assert!(log.records().pop().unwrap().local_search_evaluations > 0);
+        for island in &system.islands {
+            let cells: BTreeSet<_> = island.agents.values().map(|a| a.cell.unwrap()).collect();
+            assert_eq!(cells.len(), island.agents.len());
+        }
+    }
+}
@@ -0,0 +1,278 @@
+use crate::genome::{Genome, RealGenome};
+use crate::mutation_functions::{MutationFn, UniformMutation};
+use rand::RngCore;
+use std::fmt::Debug;
+
+/// Looks for better genes around the genes of an agent, see
+/// [`SystemBuilder::local_search`](crate::SystemBuilder::local_search).
+///
+/// The `fitness` of the genes and the values returned by `evaluate` are minimized, whatever the
+/// direction of the system, and penalized for the violated constraints if the system uses a
+/// penalty. The system keeps the best genes passed to `evaluate`, comparing them like the combats
+/// do, and every call of `evaluate` counts as a fitness evaluation.
+pub trait LocalSearchFn<G: Genome>: Debug + Send + Sync {
+    /// Returns the best genes found together with their fitness.
+    fn call(
+        &self,
+        genes: &G,
+        fitness: f64,
+        evaluate: &mut dyn FnMut(&G) -> f64,
+        domain: &G::Domain,
+        rng: &mut dyn RngCore,
+    ) -> (G, f64);
+}
+
+/// What the agent gains from the local search.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LocalSearchMode {
+    /// The agent takes the improved genes, which are passed on to its offspring.
+    #[default]
+    Lamarckian,
+    /// The agent keeps its genes and only fights with the improved fitness, so the search only
+    /// helps it to survive. Its next search starts from its genes again.
+    Baldwinian,
+}
+
+/// The local search of a memetic system.
+#[derive(Debug)]
+pub(crate) struct LocalSearchConf<G: Genome> {
+    pub(crate) search: Box<dyn LocalSearchFn<G>>,
+    pub(crate) mode: LocalSearchMode,
+    /// The chance that an agent which doesn't reproduce searches instead of fighting.
+    pub(crate) chance: f64,
+    /// The energy an agent spends on a search.
+    pub(crate) energy: u32,
+}
+
+/// Mutates the genes `iterations` times, moving to the mutated genes whenever they are better.
+#[derive(Debug, Clone, Copy)]
+pub struct HillClimbing<M> {
+    pub iterations: u32,
+    pub mutation: M,
+}
+
+impl Default for HillClimbing<UniformMutation> {
+    fn default() -> Self {
+        HillClimbing {
+            iterations: 10,
+            mutation: UniformMutation {
+                gene_mutation_chance: 1.0,
+                range: 1.0 / 100.0,
+            },
+        }
+    }
+}
+
+impl<G: Genome, M: MutationFn<G>> LocalSearchFn<G> for HillClimbing<M> {
+    fn call(
+        &self,
+        genes: &G,
+        fitness: f64,
+        evaluate: &mut dyn FnMut(&G) -> f64,
+        domain: &G::Domain,
+        rng: &mut dyn RngCore,
+    ) -> (G, f64) {
+        let (mut best, mut best_fitness) = (genes.clone(), fitness);
+        for _ in 0..self.iterations {
+            let mut candidate = best.clone();
+            self.mutation.call(&mut candidate, domain, rng);
+            let candidate_fitness = evaluate(&candidate);
+            if candidate_fitness < best_fitness {
+                best = candidate;
+                best_fitness = candidate_fitness;
+            }
+        }
+        (best, best_fitness)
+    }
+}
+
+/// Moves the genes one at a time by `step` of their domain length, up or down, keeping the moves
+/// which improve the fitness. The step is halved after every sweep through the genes which
+/// improved nothing.
+#[derive(Debug, Clone, Copy)]
+pub struct CoordinateDescent {
+    pub sweeps: u32,
+    pub step: f64,
+}
+
+impl Default for CoordinateDescent {
+    fn default() -> Self {
+        CoordinateDescent {
+            sweeps: 2,
+            step: 1.0 / 100.0,
+        }
+    }
+}
+
+impl<G: RealGenome> LocalSearchFn<G> for CoordinateDescent {
+    fn call(
+        &self,
+        genes: &G,
+        fitness: f64,
+        evaluate: &mut dyn FnMut(&G) -> f64,
+        domain: &G::Domain,
+        _rng: &mut dyn RngCore,
+    ) -> (G, f64) {
+        let (mut best, mut best_fitness) = (genes.clone(), fitness);
+        let mut step = self.step;
+        for _ in 0..self.sweeps {
+            let mut improved = false;
+            for (i, &(d_min, d_max)) in G::bounds(domain).iter().enumerate() {
+                for sign in [1.0, -1.0] {
+                    let mut candidate = best.clone();
+                    let gene = &mut candidate.as_mut()[i];
+                    let moved = (*gene + sign * step * (d_max - d_min)).clamp(d_min, d_max);
+                    if moved == *gene {
+                        continue;
+                    }
+                    *gene = moved;
+                    let candidate_fitness = evaluate(&candidate);
+                    if candidate_fitness < best_fitness {
+                        best = candidate;
+                        best_fitness = candidate_fitness;
+                        improved = true;
+                        break;
+                    }
+                }
+            }
+            if !improved {
+                step /= 2.0;
+            }
+        }
+        (best, best_fitness)
+    }
+}
+
+/// The Nelder–Mead simplex method, run for `iterations` iterations from a simplex around the
+/// genes whose edges are `initial_step` of the domain lengths. The points are kept inside the
+/// domain.
+#[derive(Debug, Clone, Copy)]
+pub struct NelderMead {
+    pub iterations: u32,
+    pub initial_step: f64,
+}
+
+impl Default for NelderMead {
+    fn default() -> Self {
+        NelderMead {
+            iterations: 20,
+            initial_step: 1.0 / 20.0,
+        }
+    }
+}
+
+impl<G: RealGenome> LocalSearchFn<G> for NelderMead {
+    fn call(
+        &self,
+        genes: &G,
+        fitness: f64,
+        evaluate: &mut dyn FnMut(&G) -> f64,
+        domain: &G::Domain,
+        _rng: &mut dyn RngCore,
+    ) -> (G, f64) {
+        let bounds = G::bounds(domain);
+        let n = bounds.len();
+        if n == 0 {
+            return (genes.clone(), fitness);
+        }
+        let to_genes = |point: &[f64]| {
+            let mut candidate = genes.clone();
+            candidate.as_mut().copy_from_slice(point);
+            candidate
+        };
+        let clamp = |point: Vec<f64>| -> Vec<f64> {
+            point
+                .into_iter()
+                .zip(bounds)
+                .map(|(x, &(d_min, d_max))| x.clamp(d_min, d_max))
+                .collect()
+        };
+
+        let mut simplex = vec![(genes.as_ref().to_vec(), fitness)];
+        for (i, &(d_min, d_max)) in bounds.iter().enumerate() {
+            let mut point = genes.as_ref().to_vec();
+            let step = self.initial_step * (d_max - d_min);
+            point[i] = if point[i] + step <= d_max { point[i] + step } else { point[i] - step };
+            let point = clamp(point);
+            let point_fitness = evaluate(&to_genes(&point));
+            simplex.push((point, point_fitness));
+        }
+
+        for _ in 0..self.iterations {
+            simplex.sort_by(|(_, f1), (_, f2)| f1.total_cmp(f2));
+            let centroid: Vec<f64> = (0..n)
+                .map(|i| simplex[..n].iter().map(|(point, _)| point[i]).sum::<f64>() / n as f64)
+                .collect();
+            let (worst, worst_fitness) = simplex[n].clone();
+            // The point on the line from the worst point through the centroid, `t` = 1 is the
+            // reflection of the worst point.
+            let along = |t: f64| clamp(centroid.iter().zip(&worst).map(|(c, w)| c + t * (c - w)).collect());
+
+            let reflected = along(1.0);
+            let reflected_fitness = evaluate(&to_genes(&reflected));
+            if reflected_fitness < simplex[0].1 {
+                let expanded = along(2.0);
+                let expanded_fitness = evaluate(&to_genes(&expanded));
+                simplex[n] = if expanded_fitness < reflected_fitness {
+                    (expanded, expanded_fitness)
+                } else {
+                    (reflected, reflected_fitness)
+                };
+            } else if reflected_fitness < simplex[n - 1].1 {
+                simplex[n] = (reflected, reflected_fitness);
+            } else {
+                let contracted = if reflected_fitness < worst_fitness { along(0.5) } else { along(-0.5) };
+                let contracted_fitness = evaluate(&to_genes(&contracted));
+                if contracted_fitness < reflected_fitness.min(worst_fitness) {
+                    simplex[n] = (contracted, contracted_fitness);
+                } else {
+                    let best = simplex[0].0.clone();
+                    for (point, point_fitness) in simplex[1..].iter_mut() {
+                        for (x, b) in point.iter_mut().zip(&best) {
+                            *x = b + 0.5 * (*x - b);
+                        }
+                        *point_fitness = evaluate(&to_genes(point));
+                    }
+                }
+            }
+        }
+
+        let (best, best_fitness) = simplex
+            .into_iter()
+            .min_by(|(_, f1), (_, f2)| f1.total_cmp(f2))
+            .unwrap();
+        (to_genes(&best), best_fitness)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fitness_functions::{FitnessFn, RastriginFitness};
+    use crate::local_search::*;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    #[test]
+    fn local_searches_improve_test() {
+        let sphere = |x: &[f64; 3]| x.iter().map(|xi| xi * xi).sum::<f64>();
+        let domain = RastriginFitness::<3>::default().domain();
+        let genes = [1.0, -2.0, 0.5];
+        let searches: [Box<dyn LocalSearchFn<[f64; 3]>>; 3] = [
+            Box::new(HillClimbing::default()),
+            Box::new(CoordinateDescent::default()),
+            Box::new(NelderMead::default()),
+        ];
+        for search in searches {
+            let mut evaluations = 0;
+            let mut evaluate = |x: &[f64; 3]| {
+                evaluations += 1;
+                sphere(x)
+            };
+            let mut rng = ChaCha8Rng::seed_from_u64(0);
+            let (best, best_fitness) = search.call(&genes, sphere(&genes), &mut evaluate, &domain, &mut rng);
+            assert!(best_fitness < sphere(&genes), "{:?}", search);
+            assert_eq!(best_fitness, sphere(&best));
+            assert!(evaluations > 0);
+        }
+    }
+}
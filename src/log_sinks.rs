@@ -0,0 +1,297 @@
+use crate::energy::EnergyFlows;
+use serde::{Deserialize, Serialize};
+use std::fmt::{Debug, Formatter};
+use std::fs::File;
+use std::io;
+use std::io::Write;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+/// The state of the whole system, logged every `log_steps` steps.
+///
+/// The statistics of the living agents are `None` when no agents are left, which stops the run,
+/// see [`TerminationReason::Extinction`](crate::termination::TerminationReason::Extinction).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LogRecord {
+    pub step: u32,
+    /// Seconds since the start of the run.
+    pub timestamp: f64,
+    pub historical_best: f64,
+    pub agents_amount: usize,
+    pub energy_sum: u32,
+    pub best_living: Option<f64>,
+    pub average_fitness: Option<f64>,
+    pub average_energy: Option<f64>,
+    /// The amount of the non-dominated solutions found so far, if the system is multi-objective.
+    pub pareto_front_size: Option<usize>,
+    /// The hypervolume of the non-dominated solutions found so far, if the system is
+    /// multi-objective.
+    pub hypervolume: Option<f64>,
+    /// The share of the living agents which satisfy the constraints.
+    pub feasibility_ratio: Option<f64>,
+    /// The fitness of the best feasible solution found so far, if any was found.
+    pub best_feasible: Option<f64>,
+    /// The fitness evaluations made so far by the local search of a memetic system.
+    pub local_search_evaluations: u64,
+    /// The energy moved by every kind of transfer so far, if the system keeps an energy ledger.
+    pub energy_flows: Option<EnergyFlows>,
+    /// The state of every island, in the order of their ids.
+    pub islands: Vec<IslandRecord>,
+}
+
+/// The state of a single island, logged together with the [`LogRecord`] of the whole system.
+///
+/// The fitness and energy statistics are `None` when the island has no agents left. The spatial
+/// statistics are only logged for the islands with a lattice, see
+/// [`SystemBuilder::lattice`](crate::SystemBuilder::lattice).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IslandRecord {
+    pub island: usize,
+    pub agents_amount: usize,
+    pub energy_sum: u32,
+    pub best_living: Option<f64>,
+    pub average_fitness: Option<f64>,
+    pub average_energy: Option<f64>,
+    /// The share of the occupied cells of the lattice.
+    pub occupancy: Option<f64>,
+    /// The average absolute difference of the fitness between the neighbouring agents.
+    pub neighbour_fitness_difference: Option<f64>,
+    /// Moran's I of the fitness, close to 1 if the neighbours have similar fitness and close to 0
+    /// if the fitness is spread at random.
+    pub fitness_autocorrelation: Option<f64>,
+}
+
+/// Receives the log records of a run.
+pub trait LogSink: Debug + Send {
+    fn log(&mut self, record: &LogRecord) -> io::Result<()>;
+}
+
+/// Drops all the records.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoLog;
+
+impl LogSink for NoLog {
+    fn log(&mut self, _record: &LogRecord) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Writes the records to a CSV file, one row per record. Every row is written as soon as it is
+/// logged, so the file can be plotted while the system is running.
+///
+/// The island records are only written if a second file is given with [`CsvLog::with_islands`].
+#[derive(Debug)]
+pub struct CsvLog {
+    file: File,
+    islands_file: Option<File>,
+}
+
+impl CsvLog {
+    /// Creates the file, or truncates it if it exists, and writes the header.
+    pub fn create(path: impl AsRef<Path>) -> io::Result<CsvLog> {
+        let mut file = File::create(path)?;
+        file.write_all(
+            b"step,timestamp,historical best,agents amount,energy sum,best living,average fitness,average energy,pareto front size,hypervolume,feasibility ratio,best feasible,local search evaluations,energy reproduction,energy combat,energy death,energy migration,energy local search,energy custom gained,energy custom lost\n"
+        )?;
+        Ok(CsvLog {
+            file,
+            islands_file: None,
+        })
+    }
+
+    /// Writes the island records to another CSV file, one row per island per record.
+    pub fn with_islands(mut self, path: impl AsRef<Path>) -> io::Result<CsvLog> {
+        let mut file = File::create(path)?;
+        file.write_all(b"step,island,agents amount,energy sum,best living,average fitness,average energy,occupancy,neighbour fitness difference,fitness autocorrelation\n")?;
+        self.islands_file = Some(file);
+        Ok(self)
+    }
+}
+
+fn optional<T: ToString>(value: Option<T>) -> String {
+    value.map(|v| v.to_string()).unwrap_or_default()
+}
+
+fn energy_flows(flows: Option<&EnergyFlows>) -> String {
+    match flows {
+        Some(f) => format!(
+            "{},{},{},{},{},{},{}",
+            f.reproduction, f.combat, f.death, f.migration, f.local_search, f.custom_gained, f.custom_lost
+        ),
+        None => ",,,,,,".to_string(),
+    }
+}
+
+impl LogSink for CsvLog {
+    fn log(&mut self, record: &LogRecord) -> io::Result<()> {
+        let line = format!(
+            "{},{},{},{},{},{},{},{},{},{},{},{},{},{}\n",
+            record.step,
+            record.timestamp,
+            record.historical_best,
+            record.agents_amount,
+            record.energy_sum,
+            optional(record.best_living),
+            optional(record.average_fitness),
+            optional(record.average_energy),
+            optional(record.pareto_front_size),
+            optional(record.hypervolume),
+            optional(record.feasibility_ratio),
+            optional(record.best_feasible),
+            record.local_search_evaluations,
+            energy_flows(record.energy_flows.as_ref())
+        );
+        self.file.write_all(line.as_bytes())?;
+
+        if let Some(islands_file) = &mut self.islands_file {
+            let mut lines = String::new();
+            for island in &record.islands {
+                lines.push_str(&format!(
+                    "{},{},{},{},{},{},{},{},{},{}\n",
+                    record.step,
+                    island.island,
+                    island.agents_amount,
+                    island.energy_sum,
+                    optional(island.best_living),
+                    optional(island.average_fitness),
+                    optional(island.average_energy),
+                    optional(island.occupancy),
+                    optional(island.neighbour_fitness_difference),
+                    optional(island.fitness_autocorrelation)
+                ));
+            }
+            islands_file.write_all(lines.as_bytes())?;
+        }
+        Ok(())
+    }
+}
+
+/// Writes the records to a file as JSON Lines, one object per record.
+#[derive(Debug)]
+pub struct JsonLinesLog {
+    file: File,
+}
+
+impl JsonLinesLog {
+    /// Creates the file, or truncates it if it exists.
+    pub fn create(path: impl AsRef<Path>) -> io::Result<JsonLinesLog> {
+        Ok(JsonLinesLog {
+            file: File::create(path)?,
+        })
+    }
+}
+
+impl LogSink for JsonLinesLog {
+    fn log(&mut self, record: &LogRecord) -> io::Result<()> {
+        let mut line = serde_json::to_vec(record)?;
+        line.push(b'\n');
+        self.file.write_all(&line)
+    }
+}
+
+/// Keeps the records in memory. The sink is a handle, clone it before passing it to the builder
+/// to read the records during or after the run.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryLog {
+    records: Arc<Mutex<Vec<LogRecord>>>,
+}
+
+impl MemoryLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn records(&self) -> Vec<LogRecord> {
+        self.records.lock().unwrap().clone()
+    }
+}
+
+impl LogSink for MemoryLog {
+    fn log(&mut self, record: &LogRecord) -> io::Result<()> {
+        self.records.lock().unwrap().push(record.clone());
+        Ok(())
+    }
+}
+
+/// Passes every record to a user supplied closure.
+pub struct CallbackLog<C>(pub C);
+
+impl<C> Debug for CallbackLog<C> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CallbackLog").finish_non_exhaustive()
+    }
+}
+
+impl<C: FnMut(&LogRecord) + Send> LogSink for CallbackLog<C> {
+    fn log(&mut self, record: &LogRecord) -> io::Result<()> {
+        (self.0)(record);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::log_sinks::*;
+    use std::fs;
+
+    #[test]
+    fn csv_log_test() {
+        let dir = std::env::temp_dir();
+        let path = dir.join(format!("emas_rs_csv_log_test_{}.csv", std::process::id()));
+        let islands_path = dir.join(format!("emas_rs_csv_log_test_islands_{}.csv", std::process::id()));
+        let mut log = CsvLog::create(&path).unwrap().with_islands(&islands_path).unwrap();
+        let record = LogRecord {
+            step: 100,
+            timestamp: 0.5,
+            historical_best: 1.0,
+            agents_amount: 20,
+            energy_sum: 200,
+            best_living: Some(2.0),
+            average_fitness: Some(3.0),
+            average_energy: Some(10.0),
+            pareto_front_size: None,
+            hypervolume: None,
+            feasibility_ratio: Some(0.75),
+            best_feasible: None,
+            local_search_evaluations: 0,
+            energy_flows: None,
+            islands: vec![
+                IslandRecord {
+                    island: 0,
+                    agents_amount: 20,
+                    energy_sum: 200,
+                    best_living: Some(2.0),
+                    average_fitness: Some(3.0),
+                    average_energy: Some(10.0),
+                    occupancy: Some(0.5),
+                    neighbour_fitness_difference: Some(1.5),
+                    fitness_autocorrelation: None,
+                },
+                IslandRecord {
+                    island: 1,
+                    agents_amount: 0,
+                    energy_sum: 0,
+                    best_living: None,
+                    average_fitness: None,
+                    average_energy: None,
+                    occupancy: None,
+                    neighbour_fitness_difference: None,
+                    fitness_autocorrelation: None,
+                },
+            ],
+        };
+        log.log(&record).unwrap();
+        drop(log);
+
+        let contents = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        let lines: Vec<_> = contents.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[1], "100,0.5,1,20,200,2,3,10,,,0.75,,0,,,,,,,");
+
+        let contents = fs::read_to_string(&islands_path).unwrap();
+        fs::remove_file(&islands_path).unwrap();
+        let lines: Vec<_> = contents.lines().collect();
+        assert_eq!(lines[1..], ["100,0,20,200,2,3,10,0.5,1.5,", "100,1,0,0,,,,,,"]);
+    }
+}
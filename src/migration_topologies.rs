@@ -0,0 +1,221 @@
+use rand::prelude::SliceRandom;
+use rand::RngCore;
+use std::fmt::Debug;
+
+/// Decides which islands the migrants of each island can go to.
+///
+/// The graph is built once, when the system is built. `adjacency(...)[i]` lists the islands
+/// the agents from island `i` can migrate to.
+pub trait MigrationTopology: Debug + Send + Sync {
+    fn adjacency(&self, island_amount: usize, rng: &mut dyn RngCore) -> Vec<Vec<usize>>;
+}
+
+/// How the migrants leaving an island are spread among its neighbours.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MigrantDistribution {
+    /// Every migrant goes to a uniformly drawn neighbour.
+    #[default]
+    Random,
+    /// The migrants are dealt to the neighbours in turn, starting from a random one, so every
+    /// neighbour gets the same amount of them, give or take one.
+    Even,
+}
+
+/// How the migrants are chosen.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum MigrationMode {
+    /// Every `migration_steps` steps some of the best agents of every island are sent away, see
+    /// [`SystemBuilder::migrations_best_amount`](crate::SystemBuilder::migrations_best_amount).
+    #[default]
+    Batch,
+    /// The agents decide to emigrate themselves: an agent which doesn't reproduce leaves its
+    /// island with the given chance if it has at least `energy_threshold` energy. The migrants
+    /// arrive at the end of the step in which they left, so the islands are synchronised after
+    /// every step.
+    AgentInitiated { energy_threshold: u32, chance: f64 },
+}
+
+/// Every island is connected with every other island.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FullyConnected;
+
+impl MigrationTopology for FullyConnected {
+    fn adjacency(&self, island_amount: usize, _rng: &mut dyn RngCore) -> Vec<Vec<usize>> {
+        (0..island_amount)
+            .map(|i| (0..island_amount).filter(|&j| j != i).collect())
+            .collect()
+    }
+}
+
+/// Island `i` sends its migrants to island `i + 1`, the last one to the first one.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Ring;
+
+impl MigrationTopology for Ring {
+    fn adjacency(&self, island_amount: usize, _rng: &mut dyn RngCore) -> Vec<Vec<usize>> {
+        (0..island_amount)
+            .map(|i| without_self(i, vec![(i + 1) % island_amount]))
+            .collect()
+    }
+}
+
+/// Island `i` sends its migrants to islands `i - 1` and `i + 1`, wrapping around.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BidirectionalRing;
+
+impl MigrationTopology for BidirectionalRing {
+    fn adjacency(&self, island_amount: usize, _rng: &mut dyn RngCore) -> Vec<Vec<usize>> {
+        (0..island_amount)
+            .map(|i| {
+                let previous = (i + island_amount - 1) % island_amount;
+                let next = (i + 1) % island_amount;
+                without_self(i, vec![previous, next])
+            })
+            .collect()
+    }
+}
+
+/// Islands are laid out row by row on a `width` x `height` grid with wrapping edges, each one
+/// is connected to the islands above, below, to the left and to the right of it.
+#[derive(Debug, Clone, Copy)]
+pub struct Torus {
+    pub width: usize,
+    pub height: usize,
+}
+
+impl MigrationTopology for Torus {
+    fn adjacency(&self, island_amount: usize, _rng: &mut dyn RngCore) -> Vec<Vec<usize>> {
+        let Torus { width, height } = *self;
+        assert_eq!(
+            width * height,
+            island_amount,
+            "The torus has to have exactly as many cells as there are islands"
+        );
+
+        (0..island_amount)
+            .map(|i| {
+                let (x, y) = (i % width, i / width);
+                let neighbours = vec![
+                    y * width + (x + width - 1) % width,
+                    y * width + (x + 1) % width,
+                    (y + height - 1) % height * width + x,
+                    (y + 1) % height * width + x,
+                ];
+                without_self(i, neighbours)
+            })
+            .collect()
+    }
+}
+
+/// The `center` island is connected with all the others, which are only connected with it.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Star {
+    pub center: usize,
+}
+
+impl MigrationTopology for Star {
+    fn adjacency(&self, island_amount: usize, _rng: &mut dyn RngCore) -> Vec<Vec<usize>> {
+        assert!(self.center < island_amount, "The center of the star has to be one of the islands");
+
+        (0..island_amount)
+            .map(|i| {
+                if i == self.center {
+                    (0..island_amount).filter(|&j| j != i).collect()
+                } else {
+                    vec![self.center]
+                }
+            })
+            .collect()
+    }
+}
+
+/// A random undirected graph in which every island has exactly `degree` neighbours.
+#[derive(Debug, Clone, Copy)]
+pub struct RandomRegular {
+    pub degree: usize,
+}
+
+impl MigrationTopology for RandomRegular {
+    fn adjacency(&self, island_amount: usize, rng: &mut dyn RngCore) -> Vec<Vec<usize>> {
+        let degree = self.degree;
+        assert!(
+            degree < island_amount && (degree * island_amount).is_multiple_of(2),
+            "A {}-regular graph on {} islands doesn't exist",
+            degree,
+            island_amount
+        );
+
+        // The pairing model: every island gets `degree` stubs, which are randomly paired up.
+        // Pairings with loops or multiple edges are rejected and drawn again.
+        let mut stubs: Vec<_> = (0..island_amount)
+            .flat_map(|i| std::iter::repeat_n(i, degree))
+            .collect();
+        'draw: for _ in 0..10_000 {
+            stubs.shuffle(rng);
+            let mut adjacency = vec![Vec::with_capacity(degree); island_amount];
+            for pair in stubs.chunks(2) {
+                let (a, b) = (pair[0], pair[1]);
+                if a == b || adjacency[a].contains(&b) {
+                    continue 'draw;
+                }
+                adjacency[a].push(b);
+                adjacency[b].push(a);
+            }
+            return adjacency;
+        }
+
+        panic!("Can't draw a {}-regular graph on {} islands", degree, island_amount)
+    }
+}
+
+/// A user supplied graph, `0[i]` lists the islands the migrants from island `i` can go to.
+#[derive(Debug, Clone)]
+pub struct AdjacencyList(pub Vec<Vec<usize>>);
+
+impl MigrationTopology for AdjacencyList {
+    fn adjacency(&self, island_amount: usize, _rng: &mut dyn RngCore) -> Vec<Vec<usize>> {
+        assert_eq!(
+            self.0.len(),
+            island_amount,
+            "The adjacency list has to have an entry for every island"
+        );
+        for (i, neighbours) in self.0.iter().enumerate() {
+            for &j in neighbours {
+                assert!(j < island_amount && j != i, "Island {} can't migrate to island {}", i, j);
+            }
+        }
+
+        self.0.clone()
+    }
+}
+
+/// Drops the island itself and the duplicates, which appear in the small rings and tori.
+fn without_self(island: usize, mut neighbours: Vec<usize>) -> Vec<usize> {
+    neighbours.retain(|&j| j != island);
+    neighbours.sort_unstable();
+    neighbours.dedup();
+    neighbours
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::migration_topologies::*;
+    use rand::thread_rng;
+
+    #[test]
+    fn torus_test() {
+        let adjacency = Torus { width: 3, height: 2 }.adjacency(6, &mut thread_rng());
+        assert_eq!(adjacency[0], vec![1, 2, 3]);
+        assert_eq!(adjacency[4], vec![1, 3, 5]);
+    }
+
+    #[test]
+    fn random_regular_test() {
+        let adjacency = RandomRegular { degree: 3 }.adjacency(8, &mut thread_rng());
+        for (i, neighbours) in adjacency.iter().enumerate() {
+            assert_eq!(neighbours.len(), 3);
+            assert!(!neighbours.contains(&i));
+            assert!(neighbours.iter().all(|&j| adjacency[j].contains(&i)));
+        }
+    }
+}
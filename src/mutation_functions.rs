@@ -0,0 +1,333 @@
+use crate::genome::{BinaryGenome, Genome, IntegerGenome, Permutation, RealGenome};
+use rand::{Rng, RngCore};
+use rand_distr::{Cauchy, Distribution, Normal};
+use std::fmt::Debug;
+
+/// Mutates the genotype of a newborn agent in place.
+///
+/// Every built-in operator mutates each gene independently with probability
+/// `gene_mutation_chance` and keeps the result inside `domain`.
+///
+/// Like the [`RecombinationFn`](crate::recombination_functions::RecombinationFn), the mutation is
+/// an instance rather than a type parameter of the system, as it carries its parameters.
+pub trait MutationFn<G: Genome>: Debug + Send + Sync {
+    fn call(&self, genes: &mut G, domain: &G::Domain, rng: &mut dyn RngCore);
+}
+
+/// Moves a gene up or down by a uniformly drawn step of up to `range` of its domain length.
+#[derive(Debug, Clone, Copy)]
+pub struct UniformMutation {
+    pub gene_mutation_chance: f64,
+    pub range: f64,
+}
+
+impl Default for UniformMutation {
+    fn default() -> Self {
+        UniformMutation {
+            gene_mutation_chance: 1.0,
+            range: 1.0 / 20.0,
+        }
+    }
+}
+
+impl<G: RealGenome> MutationFn<G> for UniformMutation {
+    fn call(&self, genes: &mut G, domain: &G::Domain, rng: &mut dyn RngCore) {
+        for (gene, &(d_min, d_max)) in genes.as_mut().iter_mut().zip(G::bounds(domain)) {
+            if rng.gen::<f64>() < self.gene_mutation_chance {
+                let mutation_value = rng.gen::<f64>() * self.range * (d_max - d_min);
+                if rng.gen() {
+                    *gene = (*gene + mutation_value).min(d_max);
+                } else {
+                    *gene = (*gene - mutation_value).max(d_min);
+                }
+            }
+        }
+    }
+}
+
+/// Adds normally distributed noise with standard deviation of `sigma` of the domain length.
+#[derive(Debug, Clone, Copy)]
+pub struct GaussianMutation {
+    pub gene_mutation_chance: f64,
+    pub sigma: f64,
+}
+
+impl Default for GaussianMutation {
+    fn default() -> Self {
+        GaussianMutation {
+            gene_mutation_chance: 0.1,
+            sigma: 0.05,
+        }
+    }
+}
+
+impl<G: RealGenome> MutationFn<G> for GaussianMutation {
+    fn call(&self, genes: &mut G, domain: &G::Domain, rng: &mut dyn RngCore) {
+        let normal = Normal::new(0.0, self.sigma).expect("sigma has to be finite and non-negative");
+        for (gene, &(d_min, d_max)) in genes.as_mut().iter_mut().zip(G::bounds(domain)) {
+            if rng.gen::<f64>() < self.gene_mutation_chance {
+                *gene = (*gene + normal.sample(rng) * (d_max - d_min)).clamp(d_min, d_max);
+            }
+        }
+    }
+}
+
+/// Adds Cauchy distributed noise scaled by `scale` of the domain length. The heavy tails make
+/// occasional long jumps, which helps to escape local minima.
+#[derive(Debug, Clone, Copy)]
+pub struct CauchyMutation {
+    pub gene_mutation_chance: f64,
+    pub scale: f64,
+}
+
+impl Default for CauchyMutation {
+    fn default() -> Self {
+        CauchyMutation {
+            gene_mutation_chance: 0.1,
+            scale: 0.01,
+        }
+    }
+}
+
+impl<G: RealGenome> MutationFn<G> for CauchyMutation {
+    fn call(&self, genes: &mut G, domain: &G::Domain, rng: &mut dyn RngCore) {
+        let cauchy = Cauchy::new(0.0, self.scale).expect("scale has to be positive");
+        for (gene, &(d_min, d_max)) in genes.as_mut().iter_mut().zip(G::bounds(domain)) {
+            if rng.gen::<f64>() < self.gene_mutation_chance {
+                *gene = (*gene + cauchy.sample(rng) * (d_max - d_min)).clamp(d_min, d_max);
+            }
+        }
+    }
+}
+
+/// Deb's polynomial mutation. Larger `eta` makes smaller perturbations more likely.
+#[derive(Debug, Clone, Copy)]
+pub struct PolynomialMutation {
+    pub gene_mutation_chance: f64,
+    pub eta: f64,
+}
+
+impl Default for PolynomialMutation {
+    fn default() -> Self {
+        PolynomialMutation {
+            gene_mutation_chance: 0.1,
+            eta: 20.0,
+        }
+    }
+}
+
+impl<G: RealGenome> MutationFn<G> for PolynomialMutation {
+    fn call(&self, genes: &mut G, domain: &G::Domain, rng: &mut dyn RngCore) {
+        for (gene, &(d_min, d_max)) in genes.as_mut().iter_mut().zip(G::bounds(domain)) {
+            if rng.gen::<f64>() < self.gene_mutation_chance {
+                let u = rng.gen::<f64>();
+                let delta = if u < 0.5 {
+                    (2.0 * u).powf(1.0 / (self.eta + 1.0)) - 1.0
+                } else {
+                    1.0 - (2.0 * (1.0 - u)).powf(1.0 / (self.eta + 1.0))
+                };
+                *gene = (*gene + delta * (d_max - d_min)).clamp(d_min, d_max);
+            }
+        }
+    }
+}
+
+/// Flips every bit independently with probability `flip_chance`.
+#[derive(Debug, Clone, Copy)]
+pub struct BitFlipMutation {
+    pub flip_chance: f64,
+}
+
+impl Default for BitFlipMutation {
+    fn default() -> Self {
+        BitFlipMutation { flip_chance: 0.01 }
+    }
+}
+
+impl<G: BinaryGenome> MutationFn<G> for BitFlipMutation {
+    fn call(&self, genes: &mut G, _domain: &G::Domain, rng: &mut dyn RngCore) {
+        for gene in genes.genes_mut() {
+            if rng.gen::<f64>() < self.flip_chance {
+                *gene = !*gene;
+            }
+        }
+    }
+}
+
+/// Replaces a gene with an integer drawn uniformly from its domain.
+#[derive(Debug, Clone, Copy)]
+pub struct RandomResetMutation {
+    pub gene_mutation_chance: f64,
+}
+
+impl Default for RandomResetMutation {
+    fn default() -> Self {
+        RandomResetMutation { gene_mutation_chance: 0.1 }
+    }
+}
+
+impl<G: IntegerGenome> MutationFn<G> for RandomResetMutation {
+    fn call(&self, genes: &mut G, domain: &G::Domain, rng: &mut dyn RngCore) {
+        let bounds = G::bounds(domain);
+        for (gene, &(d_min, d_max)) in genes.genes_mut().iter_mut().zip(bounds) {
+            if rng.gen::<f64>() < self.gene_mutation_chance {
+                *gene = rng.gen_range(d_min..=d_max);
+            }
+        }
+    }
+}
+
+/// Moves an integer gene up or down by a uniformly drawn step of `1..=max_step`.
+#[derive(Debug, Clone, Copy)]
+pub struct CreepMutation {
+    pub gene_mutation_chance: f64,
+    pub max_step: i64,
+}
+
+impl Default for CreepMutation {
+    fn default() -> Self {
+        CreepMutation {
+            gene_mutation_chance: 0.1,
+            max_step: 1,
+        }
+    }
+}
+
+impl<G: IntegerGenome> MutationFn<G> for CreepMutation {
+    fn call(&self, genes: &mut G, domain: &G::Domain, rng: &mut dyn RngCore) {
+        let bounds = G::bounds(domain);
+        for (gene, &(d_min, d_max)) in genes.genes_mut().iter_mut().zip(bounds) {
+            if rng.gen::<f64>() < self.gene_mutation_chance {
+                let step = rng.gen_range(1..=self.max_step);
+                let step = if rng.gen() { step } else { -step };
+                *gene = gene.saturating_add(step).clamp(d_min, d_max);
+            }
+        }
+    }
+}
+
+/// Swaps two random elements of a permutation, with probability `mutation_chance`.
+#[derive(Debug, Clone, Copy)]
+pub struct SwapMutation {
+    pub mutation_chance: f64,
+}
+
+impl Default for SwapMutation {
+    fn default() -> Self {
+        SwapMutation { mutation_chance: 0.5 }
+    }
+}
+
+impl MutationFn<Permutation> for SwapMutation {
+    fn call(&self, genes: &mut Permutation, _domain: &usize, rng: &mut dyn RngCore) {
+        let len = genes.0.len();
+        if len > 1 && rng.gen::<f64>() < self.mutation_chance {
+            genes.0.swap(rng.gen_range(0..len), rng.gen_range(0..len));
+        }
+    }
+}
+
+/// Reverses a random segment of a permutation, with probability `mutation_chance`. For routing
+/// problems it replaces two edges of the tour, like the 2-opt move.
+#[derive(Debug, Clone, Copy)]
+pub struct InversionMutation {
+    pub mutation_chance: f64,
+}
+
+impl Default for InversionMutation {
+    fn default() -> Self {
+        InversionMutation { mutation_chance: 0.5 }
+    }
+}
+
+impl MutationFn<Permutation> for InversionMutation {
+    fn call(&self, genes: &mut Permutation, _domain: &usize, rng: &mut dyn RngCore) {
+        let len = genes.0.len();
+        if len > 1 && rng.gen::<f64>() < self.mutation_chance {
+            let a = rng.gen_range(0..len);
+            let b = rng.gen_range(0..len);
+            genes.0[a.min(b)..=a.max(b)].reverse();
+        }
+    }
+}
+
+/// Moves a random element of a permutation to a random position, with probability
+/// `mutation_chance`.
+#[derive(Debug, Clone, Copy)]
+pub struct InsertionMutation {
+    pub mutation_chance: f64,
+}
+
+impl Default for InsertionMutation {
+    fn default() -> Self {
+        InsertionMutation { mutation_chance: 0.5 }
+    }
+}
+
+impl MutationFn<Permutation> for InsertionMutation {
+    fn call(&self, genes: &mut Permutation, _domain: &usize, rng: &mut dyn RngCore) {
+        let len = genes.0.len();
+        if len > 1 && rng.gen::<f64>() < self.mutation_chance {
+            let v = genes.0.remove(rng.gen_range(0..len));
+            genes.0.insert(rng.gen_range(0..len), v);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mutation_functions::*;
+    use rand::thread_rng;
+
+    const DOMAIN: [(f64, f64); 3] = [(-1.0, 1.0); 3];
+
+    #[test]
+    fn zero_chance_keeps_genes_test() {
+        let mut genes = [0.5, -0.5, 0.0];
+        GaussianMutation { gene_mutation_chance: 0.0, sigma: 1.0 }.call(&mut genes, &DOMAIN, &mut thread_rng());
+        assert_eq!(genes, [0.5, -0.5, 0.0]);
+    }
+
+    #[test]
+    fn mutated_genes_stay_in_domain_test() {
+        let operators: [&dyn MutationFn<[f64; 3]>; 4] = [
+            &UniformMutation { gene_mutation_chance: 1.0, range: 10.0 },
+            &GaussianMutation { gene_mutation_chance: 1.0, sigma: 10.0 },
+            &CauchyMutation { gene_mutation_chance: 1.0, scale: 10.0 },
+            &PolynomialMutation { gene_mutation_chance: 1.0, eta: 0.5 },
+        ];
+        for operator in operators {
+            let mut genes = [1.0, -1.0, 0.0];
+            for _ in 0..100 {
+                operator.call(&mut genes, &DOMAIN, &mut thread_rng());
+                assert!(genes.iter().all(|g| (-1.0..=1.0).contains(g)));
+            }
+        }
+
+        let domain = vec![(0, 3), (-2, -2)];
+        let operators: [&dyn MutationFn<Vec<i64>>; 2] = [
+            &RandomResetMutation { gene_mutation_chance: 1.0 },
+            &CreepMutation { gene_mutation_chance: 1.0, max_step: 5 },
+        ];
+        for operator in operators {
+            let mut genes = vec![3, -2];
+            for _ in 0..100 {
+                operator.call(&mut genes, &domain, &mut thread_rng());
+                assert!((0..=3).contains(&genes[0]) && genes[1] == -2);
+            }
+        }
+
+        let operators: [&dyn MutationFn<Permutation>; 3] = [
+            &SwapMutation { mutation_chance: 1.0 },
+            &InversionMutation { mutation_chance: 1.0 },
+            &InsertionMutation { mutation_chance: 1.0 },
+        ];
+        for operator in operators {
+            let mut genes = Permutation((0..10).collect());
+            for _ in 0..100 {
+                operator.call(&mut genes, &10, &mut thread_rng());
+                assert!(genes.is_valid());
+            }
+        }
+    }
+}
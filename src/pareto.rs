@@ -0,0 +1,170 @@
+use crate::genome::Genome;
+use serde::{Deserialize, Serialize};
+
+/// Whether the objectives `a` are at least as good as `b` in every objective and better in at
+/// least one of them. All the objectives are minimized.
+pub fn dominates(a: &[f64], b: &[f64]) -> bool {
+    a.iter().zip(b).all(|(x, y)| x <= y) && a.iter().zip(b).any(|(x, y)| x < y)
+}
+
+/// The crowding distance of every point, i.e. the sum over the objectives of the normalized
+/// distances between its neighbours. The extreme points get an infinite distance.
+pub fn crowding_distances(points: &[&[f64]]) -> Vec<f64> {
+    let mut distances = vec![0.0; points.len()];
+    if points.len() < 3 {
+        distances.fill(f64::INFINITY);
+        return distances;
+    }
+
+    let mut order: Vec<_> = (0..points.len()).collect();
+    for objective in 0..points[0].len() {
+        let values: Vec<_> = points.iter().map(|p| p[objective]).collect();
+        order.sort_by(|&i, &j| values[i].partial_cmp(&values[j]).unwrap());
+        let (first, last) = (order[0], order[order.len() - 1]);
+        let (min, max) = (values[first], values[last]);
+        distances[first] = f64::INFINITY;
+        distances[last] = f64::INFINITY;
+        if max > min {
+            for window in order.windows(3) {
+                distances[window[1]] += (values[window[2]] - values[window[0]]) / (max - min);
+            }
+        }
+    }
+    distances
+}
+
+/// The volume of the objective space dominated by the points and bounded by the reference point.
+/// Points which don't dominate the reference point don't contribute to it.
+pub fn hypervolume(points: &[&[f64]], reference: &[f64]) -> f64 {
+    let points = points
+        .iter()
+        .inspect(|p| assert_eq!(p.len(), reference.len(), "The reference point has to have a value for every objective"))
+        .filter(|p| p.iter().zip(reference).all(|(x, r)| x < r))
+        .copied()
+        .collect();
+    slice_volume(points, reference)
+}
+
+/// Cuts the space into slabs along the last objective, each one bounded by the volume of the
+/// points below it projected on the remaining objectives.
+fn slice_volume(mut points: Vec<&[f64]>, reference: &[f64]) -> f64 {
+    let d = reference.len();
+    if points.is_empty() {
+        return 0.0;
+    }
+    if d == 1 {
+        return reference[0] - points.iter().map(|p| p[0]).fold(f64::INFINITY, f64::min);
+    }
+
+    points.sort_by(|a, b| a[d - 1].partial_cmp(&b[d - 1]).unwrap());
+    let mut volume = 0.0;
+    for i in 0..points.len() {
+        let upper = points.get(i + 1).map_or(reference[d - 1], |p| p[d - 1]);
+        let height = upper - points[i][d - 1];
+        if height > 0.0 {
+            let projected = points[..=i].iter().map(|p| &p[..d - 1]).collect();
+            volume += height * slice_volume(projected, &reference[..d - 1]);
+        }
+    }
+    volume
+}
+
+/// A non-dominated solution of a multi-objective problem.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct ParetoSolution<G: Genome> {
+    #[serde(serialize_with = "G::serialize_genes", deserialize_with = "G::deserialize_genes")]
+    pub genes: G,
+    #[serde(with = "crate::checkpoint::f64_vec_bits")]
+    pub objectives: Vec<f64>,
+}
+
+/// The settings of a multi-objective system.
+#[derive(Debug, Clone)]
+pub(crate) struct ParetoConf {
+    pub(crate) reference_point: Vec<f64>,
+    pub(crate) archive_capacity: usize,
+}
+
+/// The non-dominated solutions found so far. When there are more of them than the capacity, the
+/// ones from the most crowded parts of the front are dropped.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub(crate) struct ParetoArchive<G: Genome> {
+    capacity: usize,
+    solutions: Vec<ParetoSolution<G>>,
+}
+
+impl<G: Genome> ParetoArchive<G> {
+    pub(crate) fn new(capacity: usize) -> Self {
+        ParetoArchive {
+            capacity,
+            solutions: Vec::new(),
+        }
+    }
+
+    /// Adds the solution unless it is dominated by, or equal to, one already in the archive.
+    pub(crate) fn insert(&mut self, genes: &G, objectives: &[f64]) -> bool {
+        if self
+            .solutions
+            .iter()
+            .any(|s| s.objectives == objectives || dominates(&s.objectives, objectives))
+        {
+            return false;
+        }
+
+        self.solutions.retain(|s| !dominates(objectives, &s.objectives));
+        self.solutions.push(ParetoSolution {
+            genes: genes.clone(),
+            objectives: objectives.to_vec(),
+        });
+
+        if self.solutions.len() > self.capacity {
+            let points: Vec<_> = self.solutions.iter().map(|s| s.objectives.as_slice()).collect();
+            let distances = crowding_distances(&points);
+            let most_crowded = (0..distances.len())
+                .min_by(|&i, &j| distances[i].partial_cmp(&distances[j]).unwrap())
+                .unwrap();
+            self.solutions.swap_remove(most_crowded);
+        }
+        true
+    }
+
+    pub(crate) fn solutions(&self) -> &[ParetoSolution<G>] {
+        &self.solutions
+    }
+
+    pub(crate) fn hypervolume(&self, reference: &[f64]) -> f64 {
+        let points: Vec<_> = self.solutions.iter().map(|s| s.objectives.as_slice()).collect();
+        hypervolume(&points, reference)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::pareto::*;
+
+    #[test]
+    fn hypervolume_test() {
+        let points: [&[f64]; 3] = [&[1.0, 3.0], &[2.0, 2.0], &[3.0, 1.0]];
+        assert_eq!(hypervolume(&points, &[4.0, 4.0]), 6.0);
+
+        let points: [&[f64]; 2] = [&[1.0, 1.0, 1.0], &[0.0, 2.0, 2.0]];
+        assert_eq!(hypervolume(&points, &[3.0, 3.0, 3.0]), 9.0);
+    }
+
+    #[test]
+    fn archive_test() {
+        let mut archive = ParetoArchive::<Vec<f64>>::new(3);
+        assert!(archive.insert(&vec![0.0], &[2.0, 2.0]));
+        assert!(!archive.insert(&vec![1.0], &[3.0, 2.0]));
+        assert!(archive.insert(&vec![2.0], &[1.0, 3.0]));
+        assert!(archive.insert(&vec![3.0], &[1.0, 1.0]));
+        assert_eq!(archive.solutions().len(), 1);
+
+        for i in 0..5 {
+            archive.insert(&vec![0.0], &[-(i as f64), i as f64]);
+        }
+        assert_eq!(archive.solutions().len(), 3);
+    }
+}
@@ -0,0 +1,195 @@
+use crate::genome::{Genome, LinearGenome, RealGenome};
+use rand::seq::index;
+use rand::{Rng, RngCore};
+use std::fmt::Debug;
+
+/// What is known about an agent looking for an opponent or being chosen as one.
+#[derive(Debug, Clone, Copy)]
+pub struct Candidate<'a, G> {
+    pub genes: &'a G,
+    pub energy: u32,
+    /// Minimized and penalized as in the combat, see
+    /// [`Combatant`](crate::conf_functions::Combatant).
+    pub fitness: f64,
+    pub violation: f64,
+}
+
+impl<G> Candidate<'_, G> {
+    fn is_better_than(&self, other: &Candidate<G>) -> bool {
+        if self.violation != other.violation {
+            return self.violation < other.violation;
+        }
+        self.fitness < other.fitness
+    }
+}
+
+/// Chooses the opponent of an agent which decided to fight, see
+/// [`SystemBuilder::partner_selection`](crate::SystemBuilder::partner_selection).
+///
+/// The agents look for their opponents in a random order, every one among the agents which
+/// haven't fought yet in the step.
+pub trait PartnerSelectionFn<G: Genome>: Debug + Send + Sync {
+    /// Returns the index of the opponent in `candidates`, which is never empty, or `None` if the
+    /// agent doesn't fight in the step.
+    fn call(&self, agent: &Candidate<G>, candidates: &[Candidate<G>], rng: &mut dyn RngCore) -> Option<usize>;
+}
+
+/// The distance between two genotypes.
+pub trait GenotypeDistanceFn<G: Genome>: Debug + Send + Sync {
+    fn call(&self, genes1: &G, genes2: &G) -> f64;
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EuclideanDistance;
+
+impl<G: RealGenome> GenotypeDistanceFn<G> for EuclideanDistance {
+    fn call(&self, genes1: &G, genes2: &G) -> f64 {
+        genes1
+            .as_ref()
+            .iter()
+            .zip(genes2.as_ref())
+            .map(|(x1, x2)| (x1 - x2).powi(2))
+            .sum::<f64>()
+            .sqrt()
+    }
+}
+
+/// The amount of the positions at which the genes differ.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HammingDistance;
+
+impl<G: LinearGenome> GenotypeDistanceFn<G> for HammingDistance
+    where
+        G::Gene: PartialEq
+{
+    fn call(&self, genes1: &G, genes2: &G) -> f64 {
+        genes1
+            .genes()
+            .iter()
+            .zip(genes2.genes())
+            .filter(|(g1, g2)| g1 != g2)
+            .count() as f64
+    }
+}
+
+/// Any agent is as likely to be the opponent, as without a partner selection.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UniformPartner;
+
+impl<G: Genome> PartnerSelectionFn<G> for UniformPartner {
+    fn call(&self, _agent: &Candidate<G>, candidates: &[Candidate<G>], rng: &mut dyn RngCore) -> Option<usize> {
+        Some(rng.gen_range(0..candidates.len()))
+    }
+}
+
+/// The best of `size` randomly drawn candidates is the opponent, so the weaker agents lose
+/// energy faster the bigger the tournament is.
+#[derive(Debug, Clone, Copy)]
+pub struct TournamentPartner {
+    pub size: usize,
+}
+
+impl<G: Genome> PartnerSelectionFn<G> for TournamentPartner {
+    fn call(&self, _agent: &Candidate<G>, candidates: &[Candidate<G>], rng: &mut dyn RngCore) -> Option<usize> {
+        let size = self.size.clamp(1, candidates.len());
+        index::sample(rng, candidates.len(), size)
+            .into_iter()
+            .reduce(|best, i| if candidates[i].is_better_than(&candidates[best]) { i } else { best })
+    }
+}
+
+/// The candidate with the closest genotype is the opponent, so the agents compete within their
+/// niches.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NearestGenotypePartner<D> {
+    pub distance: D,
+}
+
+impl<G: Genome, D: GenotypeDistanceFn<G>> PartnerSelectionFn<G> for NearestGenotypePartner<D> {
+    fn call(&self, agent: &Candidate<G>, candidates: &[Candidate<G>], _rng: &mut dyn RngCore) -> Option<usize> {
+        candidates
+            .iter()
+            .map(|candidate| self.distance.call(agent.genes, candidate.genes))
+            .enumerate()
+            .min_by(|(_, d1), (_, d2)| d1.total_cmp(d2))
+            .map(|(i, _)| i)
+    }
+}
+
+/// The candidate with the closest energy is the opponent.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SimilarEnergyPartner;
+
+impl<G: Genome> PartnerSelectionFn<G> for SimilarEnergyPartner {
+    fn call(&self, agent: &Candidate<G>, candidates: &[Candidate<G>], _rng: &mut dyn RngCore) -> Option<usize> {
+        candidates
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, candidate)| candidate.energy.abs_diff(agent.energy))
+            .map(|(i, _)| i)
+    }
+}
+
+/// The opponent is drawn uniformly among the neighbours of the agent, the candidates whose
+/// genotype is at most `radius` away from its own, so the agents only compete within their
+/// niches. The agent doesn't fight if it has no neighbours.
+///
+/// The neighbourhood is a part of the search space, which doesn't change as the agents are born
+/// and die. For a neighbourhood in the space the agents live in, see
+/// [`SystemBuilder::lattice`](crate::SystemBuilder::lattice).
+#[derive(Debug, Clone, Copy)]
+pub struct NeighbourhoodPartner<D> {
+    pub radius: f64,
+    pub distance: D,
+}
+
+impl<G: Genome, D: GenotypeDistanceFn<G>> PartnerSelectionFn<G> for NeighbourhoodPartner<D> {
+    fn call(&self, agent: &Candidate<G>, candidates: &[Candidate<G>], rng: &mut dyn RngCore) -> Option<usize> {
+        let neighbours: Vec<_> = candidates
+            .iter()
+            .enumerate()
+            .filter(|(_, candidate)| self.distance.call(agent.genes, candidate.genes) <= self.radius)
+            .map(|(i, _)| i)
+            .collect();
+        if neighbours.is_empty() {
+            return None;
+        }
+        Some(neighbours[rng.gen_range(0..neighbours.len())])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::partner_selection::*;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    #[test]
+    fn partner_selections_test() {
+        let genes = [[0.0, 0.0], [5.0, 5.0], [1.0, 0.0], [3.0, 3.0]];
+        let candidates: Vec<_> = genes
+            .iter()
+            .enumerate()
+            .map(|(i, genes)| Candidate {
+                genes,
+                energy: 10 * i as u32,
+                fitness: genes[0],
+                violation: 0.0,
+            })
+            .collect();
+        let (agent, candidates) = (&candidates[0], &candidates[1..]);
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+
+        let nearest = NearestGenotypePartner { distance: EuclideanDistance };
+        assert_eq!(nearest.call(agent, candidates, &mut rng), Some(1));
+        assert_eq!(SimilarEnergyPartner.call(agent, candidates, &mut rng), Some(0));
+        let tournament = TournamentPartner { size: 3 };
+        assert_eq!(tournament.call(agent, candidates, &mut rng), Some(1));
+        let neighbourhood = NeighbourhoodPartner {
+            radius: 2.0,
+            distance: EuclideanDistance,
+        };
+        assert_eq!(neighbourhood.call(&candidates[2], &candidates[..1], &mut rng), None);
+        assert_eq!(neighbourhood.call(agent, candidates, &mut rng), Some(1));
+    }
+}
@@ -0,0 +1,411 @@
+use crate::genome::{Genome, LinearGenome, Permutation, RealGenome};
+use rand::{Rng, RngCore};
+use std::fmt::Debug;
+
+/// Builds the genotypes of two children from the genotypes of their parents.
+///
+/// `domain` is the domain of the fitness function, operators that can produce genes outside
+/// of the parents' range clamp the result to it.
+///
+/// Unlike the [`CombatWinChanceFn`](crate::conf_functions::CombatWinChanceFn), which is a type
+/// parameter of the system, the operators are passed as instances: most of them carry their
+/// parameters, e.g. the `alpha` of [`BlendCrossover`], and the default one depends on the
+/// genome, see [`Genome::default_recombination`].
+pub trait RecombinationFn<G: Genome>: Debug + Send + Sync {
+    fn call(
+        &self,
+        parent1: &G,
+        parent2: &G,
+        domain: &G::Domain,
+        rng: &mut dyn RngCore,
+    ) -> (G, G);
+}
+
+/// Cuts both parents at a single random point and swaps the tails.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SinglePointCrossover;
+
+impl<G: LinearGenome> RecombinationFn<G> for SinglePointCrossover {
+    fn call(
+        &self,
+        parent1: &G,
+        parent2: &G,
+        _domain: &G::Domain,
+        rng: &mut dyn RngCore,
+    ) -> (G, G) {
+        let mut ch1 = parent1.clone();
+        let mut ch2 = parent2.clone();
+
+        let cut_point = rng.gen_range(0..parent1.genes().len());
+        ch1.genes_mut()[cut_point..].copy_from_slice(&parent2.genes()[cut_point..]);
+        ch2.genes_mut()[cut_point..].copy_from_slice(&parent1.genes()[cut_point..]);
+
+        (ch1, ch2)
+    }
+}
+
+/// Cuts both parents at two random points and swaps the segment between them.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TwoPointCrossover;
+
+impl<G: LinearGenome> RecombinationFn<G> for TwoPointCrossover {
+    fn call(
+        &self,
+        parent1: &G,
+        parent2: &G,
+        _domain: &G::Domain,
+        rng: &mut dyn RngCore,
+    ) -> (G, G) {
+        let mut ch1 = parent1.clone();
+        let mut ch2 = parent2.clone();
+
+        let len = parent1.genes().len();
+        let a = rng.gen_range(0..=len);
+        let b = rng.gen_range(0..=len);
+        let (from, to) = (a.min(b), a.max(b));
+        ch1.genes_mut()[from..to].copy_from_slice(&parent2.genes()[from..to]);
+        ch2.genes_mut()[from..to].copy_from_slice(&parent1.genes()[from..to]);
+
+        (ch1, ch2)
+    }
+}
+
+/// Swaps every gene independently with the given probability.
+#[derive(Debug, Clone, Copy)]
+pub struct UniformCrossover {
+    pub swap_chance: f64,
+}
+
+impl Default for UniformCrossover {
+    fn default() -> Self {
+        UniformCrossover { swap_chance: 0.5 }
+    }
+}
+
+impl<G: LinearGenome> RecombinationFn<G> for UniformCrossover {
+    fn call(
+        &self,
+        parent1: &G,
+        parent2: &G,
+        _domain: &G::Domain,
+        rng: &mut dyn RngCore,
+    ) -> (G, G) {
+        let mut ch1 = parent1.clone();
+        let mut ch2 = parent2.clone();
+
+        let genes = ch1.genes_mut().iter_mut().zip(ch2.genes_mut());
+        for (g1, g2) in genes {
+            if rng.gen::<f64>() < self.swap_chance {
+                std::mem::swap(g1, g2);
+            }
+        }
+
+        (ch1, ch2)
+    }
+}
+
+/// Children are weighted averages of the parents, with a single random weight per recombination.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ArithmeticCrossover;
+
+impl<G: RealGenome> RecombinationFn<G> for ArithmeticCrossover {
+    fn call(
+        &self,
+        parent1: &G,
+        parent2: &G,
+        _domain: &G::Domain,
+        rng: &mut dyn RngCore,
+    ) -> (G, G) {
+        let mut ch1 = parent1.clone();
+        let mut ch2 = parent2.clone();
+
+        let weight = rng.gen::<f64>();
+        let (p1, p2) = (parent1.as_ref(), parent2.as_ref());
+        for (i, (g1, g2)) in ch1.as_mut().iter_mut().zip(ch2.as_mut()).enumerate() {
+            *g1 = weight * p1[i] + (1.0 - weight) * p2[i];
+            *g2 = (1.0 - weight) * p1[i] + weight * p2[i];
+        }
+
+        (ch1, ch2)
+    }
+}
+
+/// BLX-alpha: every gene is drawn uniformly from the parents' range extended by `alpha` of its
+/// length on both sides.
+#[derive(Debug, Clone, Copy)]
+pub struct BlendCrossover {
+    pub alpha: f64,
+}
+
+impl Default for BlendCrossover {
+    fn default() -> Self {
+        BlendCrossover { alpha: 0.5 }
+    }
+}
+
+impl<G: RealGenome> RecombinationFn<G> for BlendCrossover {
+    fn call(
+        &self,
+        parent1: &G,
+        parent2: &G,
+        domain: &G::Domain,
+        rng: &mut dyn RngCore,
+    ) -> (G, G) {
+        let mut ch1 = parent1.clone();
+        let mut ch2 = parent2.clone();
+
+        let (p1, p2) = (parent1.as_ref(), parent2.as_ref());
+        let bounds = G::bounds(domain);
+        for (i, (g1, g2)) in ch1.as_mut().iter_mut().zip(ch2.as_mut()).enumerate() {
+            let (d_min, d_max) = bounds[i];
+            let low = p1[i].min(p2[i]);
+            let high = p1[i].max(p2[i]);
+            let extension = self.alpha * (high - low);
+            let low = (low - extension).max(d_min);
+            let high = (high + extension).min(d_max);
+
+            *g1 = low + rng.gen::<f64>() * (high - low);
+            *g2 = low + rng.gen::<f64>() * (high - low);
+        }
+
+        (ch1, ch2)
+    }
+}
+
+/// SBX, simulated binary crossover. Larger `eta` keeps the children closer to their parents.
+#[derive(Debug, Clone, Copy)]
+pub struct SimulatedBinaryCrossover {
+    pub eta: f64,
+}
+
+impl Default for SimulatedBinaryCrossover {
+    fn default() -> Self {
+        SimulatedBinaryCrossover { eta: 15.0 }
+    }
+}
+
+impl<G: RealGenome> RecombinationFn<G> for SimulatedBinaryCrossover {
+    fn call(
+        &self,
+        parent1: &G,
+        parent2: &G,
+        domain: &G::Domain,
+        rng: &mut dyn RngCore,
+    ) -> (G, G) {
+        let mut ch1 = parent1.clone();
+        let mut ch2 = parent2.clone();
+
+        let (p1, p2) = (parent1.as_ref(), parent2.as_ref());
+        let bounds = G::bounds(domain);
+        for (i, (g1, g2)) in ch1.as_mut().iter_mut().zip(ch2.as_mut()).enumerate() {
+            let u = rng.gen::<f64>();
+            let beta = if u <= 0.5 {
+                (2.0 * u).powf(1.0 / (self.eta + 1.0))
+            } else {
+                (1.0 / (2.0 * (1.0 - u))).powf(1.0 / (self.eta + 1.0))
+            };
+
+            let (d_min, d_max) = bounds[i];
+            let mean = (p1[i] + p2[i]) / 2.0;
+            let spread = beta * (p1[i] - p2[i]) / 2.0;
+            *g1 = (mean + spread).clamp(d_min, d_max);
+            *g2 = (mean - spread).clamp(d_min, d_max);
+        }
+
+        (ch1, ch2)
+    }
+}
+
+/// Draws the bounds of a segment `from..to` of a permutation of length `len`.
+fn random_segment(len: usize, rng: &mut dyn RngCore) -> (usize, usize) {
+    let a = rng.gen_range(0..=len);
+    let b = rng.gen_range(0..=len);
+    (a.min(b), a.max(b))
+}
+
+/// `positions[v]` is the index of `v` in the permutation.
+fn positions(permutation: &[usize]) -> Vec<usize> {
+    let mut positions = vec![0; permutation.len()];
+    for (i, &v) in permutation.iter().enumerate() {
+        positions[v] = i;
+    }
+    positions
+}
+
+/// OX: the child keeps a random segment of one parent, the remaining elements are taken in the
+/// order they appear in the other parent, starting after the segment.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OrderCrossover;
+
+impl OrderCrossover {
+    fn child(segment_parent: &[usize], order_parent: &[usize], (from, to): (usize, usize)) -> Vec<usize> {
+        let len = segment_parent.len();
+        let mut in_segment = vec![false; len];
+        for &v in &segment_parent[from..to] {
+            in_segment[v] = true;
+        }
+
+        let mut child = segment_parent.to_vec();
+        let rest = (0..len)
+            .map(|i| order_parent[(to + i) % len])
+            .filter(|&v| !in_segment[v]);
+        let free = (0..len - (to - from)).map(|i| (to + i) % len);
+        for (position, v) in free.zip(rest) {
+            child[position] = v;
+        }
+        child
+    }
+}
+
+impl RecombinationFn<Permutation> for OrderCrossover {
+    fn call(
+        &self,
+        parent1: &Permutation,
+        parent2: &Permutation,
+        _domain: &usize,
+        rng: &mut dyn RngCore,
+    ) -> (Permutation, Permutation) {
+        let segment = random_segment(parent1.0.len(), rng);
+        (
+            Permutation(Self::child(&parent1.0, &parent2.0, segment)),
+            Permutation(Self::child(&parent2.0, &parent1.0, segment)),
+        )
+    }
+}
+
+/// PMX: the child keeps a random segment of one parent and the other positions of the other
+/// parent, the duplicates are resolved with the mapping between the two segments.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PartiallyMappedCrossover;
+
+impl PartiallyMappedCrossover {
+    fn child(segment_parent: &[usize], other_parent: &[usize], (from, to): (usize, usize)) -> Vec<usize> {
+        let segment_positions = positions(segment_parent);
+        let in_segment = |v: usize| (from..to).contains(&segment_positions[v]);
+
+        let mut child = other_parent.to_vec();
+        child[from..to].copy_from_slice(&segment_parent[from..to]);
+        for i in (0..from).chain(to..child.len()) {
+            let mut v = other_parent[i];
+            while in_segment(v) {
+                v = other_parent[segment_positions[v]];
+            }
+            child[i] = v;
+        }
+        child
+    }
+}
+
+impl RecombinationFn<Permutation> for PartiallyMappedCrossover {
+    fn call(
+        &self,
+        parent1: &Permutation,
+        parent2: &Permutation,
+        _domain: &usize,
+        rng: &mut dyn RngCore,
+    ) -> (Permutation, Permutation) {
+        let segment = random_segment(parent1.0.len(), rng);
+        (
+            Permutation(Self::child(&parent1.0, &parent2.0, segment)),
+            Permutation(Self::child(&parent2.0, &parent1.0, segment)),
+        )
+    }
+}
+
+/// CX: the positions are split into the cycles of the mapping between the parents, every
+/// element keeps its position from one of the parents, taken in turns cycle by cycle.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CycleCrossover;
+
+impl RecombinationFn<Permutation> for CycleCrossover {
+    fn call(
+        &self,
+        parent1: &Permutation,
+        parent2: &Permutation,
+        _domain: &usize,
+        _rng: &mut dyn RngCore,
+    ) -> (Permutation, Permutation) {
+        let (p1, p2) = (&parent1.0, &parent2.0);
+        let p1_positions = positions(p1);
+        let mut ch1 = p1.clone();
+        let mut ch2 = p2.clone();
+
+        let mut visited = vec![false; p1.len()];
+        let mut swap = false;
+        for start in 0..p1.len() {
+            if visited[start] {
+                continue;
+            }
+            let mut i = start;
+            while !visited[i] {
+                visited[i] = true;
+                if swap {
+                    ch1[i] = p2[i];
+                    ch2[i] = p1[i];
+                }
+                i = p1_positions[p2[i]];
+            }
+            swap = !swap;
+        }
+
+        (Permutation(ch1), Permutation(ch2))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::recombination_functions::*;
+    use rand::thread_rng;
+
+    const DOMAIN: [(f64, f64); 4] = [(-1.0, 1.0); 4];
+
+    #[test]
+    fn single_point_keeps_parent_genes_test() {
+        let p1 = [0.1, 0.2, 0.3, 0.4];
+        let p2 = [-0.1, -0.2, -0.3, -0.4];
+        let (ch1, ch2) = SinglePointCrossover.call(&p1, &p2, &DOMAIN, &mut thread_rng());
+        for i in 0..4 {
+            assert!((ch1[i] == p1[i] && ch2[i] == p2[i]) || (ch1[i] == p2[i] && ch2[i] == p1[i]));
+        }
+    }
+
+    #[test]
+    fn children_stay_in_domain_test() {
+        let p1 = vec![1.0, -1.0, 0.9, -0.9];
+        let p2 = vec![-1.0, 1.0, -0.9, 0.9];
+        let domain = DOMAIN.to_vec();
+        let operators: [&dyn RecombinationFn<Vec<f64>>; 3] = [
+            &BlendCrossover { alpha: 2.0 },
+            &SimulatedBinaryCrossover { eta: 0.5 },
+            &ArithmeticCrossover,
+        ];
+        for operator in operators {
+            for _ in 0..100 {
+                let (ch1, ch2) = operator.call(&p1, &p2, &domain, &mut thread_rng());
+                assert!(ch1.iter().chain(ch2.iter()).all(|g| (-1.0..=1.0).contains(g)));
+            }
+        }
+    }
+
+    #[test]
+    fn children_are_permutations_test() {
+        let operators: [&dyn RecombinationFn<Permutation>; 3] = [
+            &OrderCrossover,
+            &PartiallyMappedCrossover,
+            &CycleCrossover,
+        ];
+        for operator in operators {
+            for _ in 0..100 {
+                let p1 = Permutation::random(&10, &mut thread_rng());
+                let p2 = Permutation::random(&10, &mut thread_rng());
+                let (ch1, ch2) = operator.call(&p1, &p2, &10, &mut thread_rng());
+                assert!(ch1.is_valid() && ch2.is_valid());
+            }
+        }
+
+        let p1 = Permutation(vec![0, 1, 2, 3, 4, 5, 6, 7]);
+        let p2 = Permutation(vec![7, 6, 5, 4, 3, 2, 1, 0]);
+        assert_eq!(OrderCrossover::child(&p1.0, &p2.0, (2, 5)), vec![6, 5, 2, 3, 4, 1, 0, 7]);
+        assert_eq!(PartiallyMappedCrossover::child(&p1.0, &p2.0, (2, 5)), vec![7, 6, 2, 3, 4, 5, 1, 0]);
+    }
+}
@@ -0,0 +1,164 @@
+use crate::genome::Genome;
+use crate::pareto::ParetoSolution;
+use std::time::{Duration, Instant};
+
+/// Conditions, apart from the step limit, on which the run is stopped. The run stops as soon as
+/// any of the set conditions is met.
+///
+/// The islands are synchronised after every step while any condition is set, so that the run
+/// stops right after the step in which the condition was met, see
+/// [`TerminationCriteria::checked_every_step`]. Otherwise they only synchronise after migrations
+/// and after logging.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct TerminationCriteria {
+    pub(crate) target_fitness: Option<f64>,
+    pub(crate) stagnation_steps: Option<u32>,
+    pub(crate) time_limit: Option<Duration>,
+    pub(crate) max_evaluations: Option<u64>,
+}
+
+impl TerminationCriteria {
+    /// Whether the islands have to be synchronised after every step, so that the reported steps
+    /// and evaluations are the ones at which a condition was met.
+    pub(crate) fn checked_every_step(&self) -> bool {
+        self.target_fitness.is_some()
+            || self.stagnation_steps.is_some()
+            || self.time_limit.is_some()
+            || self.max_evaluations.is_some()
+    }
+}
+
+/// The criterion which stopped the run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TerminationReason {
+    /// All the configured steps were made.
+    Steps,
+    /// The best feasible solution reached the target fitness.
+    TargetFitness,
+    /// The best feasible solution didn't improve for the configured amount of steps.
+    Stagnation,
+    /// The run took longer than the time limit.
+    TimeLimit,
+    /// The fitness function was evaluated more times than allowed.
+    Evaluations,
+    /// All the agents died, e.g. because of a high death threshold, see
+    /// [`EnergyPolicy::death_threshold`](crate::energy::EnergyPolicy::death_threshold).
+    Extinction,
+}
+
+/// The outcome of [`System::run`](crate::System::run).
+#[derive(Debug, Clone)]
+pub struct RunResult<G: Genome> {
+    pub best_sol: G,
+    pub best_fitness: f64,
+    pub steps: u32,
+    pub evaluations: u64,
+    pub elapsed: Duration,
+    pub termination: TerminationReason,
+    /// The best solution which satisfies the constraints, if any was found. Without constraints
+    /// it is the best solution.
+    pub best_feasible_sol: Option<G>,
+    pub best_feasible_fitness: Option<f64>,
+    /// Empty unless the system is multi-objective, see [`System::pareto_front`](crate::System::pareto_front).
+    pub pareto_front: Vec<ParetoSolution<G>>,
+    /// Empty unless the system has an elite island, see [`System::elite`](crate::System::elite).
+    pub elite: Vec<EliteSolution<G>>,
+}
+
+/// An agent of the elite island, see
+/// [`SystemBuilder::elite_island`](crate::SystemBuilder::elite_island).
+#[derive(Debug, Clone)]
+pub struct EliteSolution<G: Genome> {
+    pub genes: G,
+    pub fitness: f64,
+    /// Empty unless the system is multi-objective.
+    pub objectives: Vec<f64>,
+    /// The amount of the combats won.
+    pub prestige: u32,
+}
+
+/// Follows the progress of a run and decides when it has to stop.
+#[derive(Debug)]
+pub(crate) struct TerminationTracker {
+    criteria: TerminationCriteria,
+    max_steps: u32,
+    start: Instant,
+    best_fitness: f64,
+    last_improvement: u32,
+}
+
+impl TerminationTracker {
+    /// `best_fitness` is the best fitness found until the `last_improvement` step, which are
+    /// carried over when a run is resumed.
+    pub(crate) fn new(
+        criteria: TerminationCriteria,
+        max_steps: u32,
+        start: Instant,
+        best_fitness: f64,
+        last_improvement: u32,
+    ) -> Self {
+        TerminationTracker {
+            criteria,
+            max_steps,
+            start,
+            best_fitness,
+            last_improvement,
+        }
+    }
+
+    pub(crate) fn last_improvement(&self) -> u32 {
+        self.last_improvement
+    }
+
+    pub(crate) fn check(
+        &mut self,
+        steps: u32,
+        best_fitness: f64,
+        evaluations: u64,
+        agents_amount: usize,
+    ) -> Option<TerminationReason> {
+        if best_fitness < self.best_fitness {
+            self.best_fitness = best_fitness;
+            self.last_improvement = steps;
+        }
+
+        if agents_amount == 0 {
+            return Some(TerminationReason::Extinction);
+        }
+        let criteria = &self.criteria;
+        if criteria.target_fitness.is_some_and(|target| best_fitness <= target) {
+            return Some(TerminationReason::TargetFitness);
+        }
+        if criteria.stagnation_steps.is_some_and(|k| steps - self.last_improvement >= k) {
+            return Some(TerminationReason::Stagnation);
+        }
+        if criteria.time_limit.is_some_and(|limit| self.start.elapsed() >= limit) {
+            return Some(TerminationReason::TimeLimit);
+        }
+        if criteria.max_evaluations.is_some_and(|max| evaluations >= max) {
+            return Some(TerminationReason::Evaluations);
+        }
+        if steps >= self.max_steps {
+            return Some(TerminationReason::Steps);
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::termination::*;
+
+    #[test]
+    fn stagnation_test() {
+        let criteria = TerminationCriteria {
+            stagnation_steps: Some(100),
+            ..Default::default()
+        };
+        let mut tracker = TerminationTracker::new(criteria, 1000, Instant::now(), f64::INFINITY, 0);
+        assert_eq!(tracker.check(50, 10.0, 0, 1), None);
+        assert_eq!(tracker.check(100, 5.0, 0, 1), None);
+        assert_eq!(tracker.check(150, 5.0, 0, 1), None);
+        assert_eq!(tracker.check(200, 5.0, 0, 1), Some(TerminationReason::Stagnation));
+    }
+}
@@ -0,0 +1,237 @@
+use crate::fitness_functions::FitnessFn;
+use crate::genome::Permutation;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// The travelling salesman problem: the fitness of a tour is its length, including the way back
+/// from the last city to the first one.
+#[derive(Debug, Clone)]
+pub struct TspFitness {
+    pub name: String,
+    distances: Vec<Vec<f64>>,
+}
+
+impl TspFitness {
+    /// `distances[i][j]` is the distance from city `i` to city `j`.
+    pub fn new(distances: Vec<Vec<f64>>) -> Self {
+        assert!(
+            distances.iter().all(|row| row.len() == distances.len()),
+            "The distance matrix has to be square"
+        );
+        TspFitness {
+            name: String::new(),
+            distances,
+        }
+    }
+
+    /// Cities on a plane, with the euclidean distances between them.
+    pub fn from_coordinates(coordinates: &[(f64, f64)]) -> Self {
+        Self::new(distance_matrix(coordinates, |(x1, y1), (x2, y2)| (x1 - x2).hypot(y1 - y2)))
+    }
+
+    /// Loads a symmetric TSP instance from a TSPLIB `.tsp` file.
+    pub fn from_tsplib(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::parse_tsplib(&fs::read_to_string(path)?)
+    }
+
+    /// Parses a symmetric TSP instance in the TSPLIB format.
+    ///
+    /// The `EUC_2D`, `CEIL_2D`, `ATT` and `GEO` edge weight types are supported, as well as the
+    /// `EXPLICIT` one with the `FULL_MATRIX` and the (upper or lower, with or without diagonal)
+    /// row formats.
+    pub fn parse_tsplib(contents: &str) -> io::Result<Self> {
+        let mut name = String::new();
+        let mut dimension = None;
+        let mut edge_weight_type = None;
+        let mut edge_weight_format = None;
+        let mut coordinates = Vec::new();
+        let mut weights = Vec::new();
+
+        let mut lines = contents.lines().map(str::trim).filter(|line| !line.is_empty()).peekable();
+        while let Some(line) = lines.next() {
+            let (key, value) = match line.split_once(':') {
+                Some((key, value)) => (key.trim(), value.trim()),
+                None => (line, ""),
+            };
+            match key {
+                "NAME" => name = value.to_string(),
+                "TYPE" if value != "TSP" => {
+                    return Err(invalid_data(format!("Only the TSP instances are supported, not {}", value)))
+                }
+                "DIMENSION" => {
+                    dimension = Some(value.parse::<usize>().map_err(|_| invalid_data(format!("Invalid dimension {}", value)))?)
+                }
+                "EDGE_WEIGHT_TYPE" => edge_weight_type = Some(value.to_string()),
+                "EDGE_WEIGHT_FORMAT" => edge_weight_format = Some(value.to_string()),
+                "NODE_COORD_SECTION" => {
+                    while let Some(line) = lines.next_if(|line| starts_with_number(line)) {
+                        let numbers = parse_numbers(line)?;
+                        if numbers.len() != 3 {
+                            return Err(invalid_data(format!("Invalid node coordinates: {}", line)));
+                        }
+                        coordinates.push((numbers[1], numbers[2]));
+                    }
+                }
+                "EDGE_WEIGHT_SECTION" => {
+                    while let Some(line) = lines.next_if(|line| starts_with_number(line)) {
+                        weights.extend(parse_numbers(line)?);
+                    }
+                }
+                "DISPLAY_DATA_SECTION" => while lines.next_if(|line| starts_with_number(line)).is_some() {},
+                "EOF" => break,
+                _ => {}
+            }
+        }
+
+        let dimension = dimension.ok_or_else(|| invalid_data("The dimension is missing".to_string()))?;
+        let edge_weight_type = edge_weight_type.ok_or_else(|| invalid_data("The edge weight type is missing".to_string()))?;
+        let distances = if edge_weight_type == "EXPLICIT" {
+            let format = edge_weight_format.ok_or_else(|| invalid_data("The edge weight format is missing".to_string()))?;
+            explicit_distances(&weights, dimension, &format)?
+        } else {
+            if coordinates.len() != dimension {
+                return Err(invalid_data(format!("Expected {} nodes, found {}", dimension, coordinates.len())));
+            }
+            let distance: fn((f64, f64), (f64, f64)) -> f64 = match edge_weight_type.as_str() {
+                "EUC_2D" => |(x1, y1), (x2, y2)| (x1 - x2).hypot(y1 - y2).round(),
+                "CEIL_2D" => |(x1, y1), (x2, y2)| (x1 - x2).hypot(y1 - y2).ceil(),
+                "ATT" => |(x1, y1), (x2, y2)| {
+                    let r = (((x1 - x2).powi(2) + (y1 - y2).powi(2)) / 10.0).sqrt();
+                    let t = r.round();
+                    if t < r { t + 1.0 } else { t }
+                },
+                "GEO" => geo_distance,
+                other => return Err(invalid_data(format!("The {} edge weight type isn't supported", other))),
+            };
+            distance_matrix(&coordinates, distance)
+        };
+
+        Ok(TspFitness { name, distances })
+    }
+
+    pub fn cities_amount(&self) -> usize {
+        self.distances.len()
+    }
+}
+
+impl FitnessFn for TspFitness {
+    type Genome = Permutation;
+
+    fn domain(&self) -> usize {
+        self.cities_amount()
+    }
+
+    fn call(&self, args: &Permutation) -> f64 {
+        let tour = &args.0;
+        tour.iter()
+            .zip(tour.iter().cycle().skip(1))
+            .map(|(&from, &to)| self.distances[from][to])
+            .sum()
+    }
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn starts_with_number(line: &str) -> bool {
+    line.starts_with(|c: char| c.is_ascii_digit() || c == '-' || c == '+' || c == '.')
+}
+
+fn parse_numbers(line: &str) -> io::Result<Vec<f64>> {
+    line.split_whitespace()
+        .map(|n| n.parse().map_err(|_| invalid_data(format!("Invalid number {}", n))))
+        .collect()
+}
+
+fn distance_matrix(coordinates: &[(f64, f64)], distance: impl Fn((f64, f64), (f64, f64)) -> f64) -> Vec<Vec<f64>> {
+    coordinates
+        .iter()
+        .map(|&c1| coordinates.iter().map(|&c2| distance(c1, c2)).collect())
+        .collect()
+}
+
+/// The distance on the idealized sphere of the Earth, as defined by TSPLIB, where the
+/// coordinates are the latitude and the longitude in the `DDD.MM` format.
+fn geo_distance((x1, y1): (f64, f64), (x2, y2): (f64, f64)) -> f64 {
+    // The truncated value is part of the definition, the published optima depend on it.
+    #[allow(clippy::approx_constant)]
+    const PI: f64 = 3.141592;
+    const RRR: f64 = 6378.388;
+    let radians = |x: f64| {
+        let degrees = x.trunc();
+        PI * (degrees + 5.0 * (x - degrees) / 3.0) / 180.0
+    };
+    let (latitude1, longitude1) = (radians(x1), radians(y1));
+    let (latitude2, longitude2) = (radians(x2), radians(y2));
+    let q1 = (longitude1 - longitude2).cos();
+    let q2 = (latitude1 - latitude2).cos();
+    let q3 = (latitude1 + latitude2).cos();
+    (RRR * (0.5 * ((1.0 + q1) * q2 - (1.0 - q1) * q3)).acos() + 1.0).trunc()
+}
+
+fn explicit_distances(weights: &[f64], dimension: usize, format: &str) -> io::Result<Vec<Vec<f64>>> {
+    // The pairs (i, j), in the order in which their weights are listed.
+    let pairs: Vec<(usize, usize)> = match format {
+        "FULL_MATRIX" => (0..dimension).flat_map(|i| (0..dimension).map(move |j| (i, j))).collect(),
+        "UPPER_ROW" => (0..dimension).flat_map(|i| (i + 1..dimension).map(move |j| (i, j))).collect(),
+        "LOWER_ROW" => (0..dimension).flat_map(|i| (0..i).map(move |j| (i, j))).collect(),
+        "UPPER_DIAG_ROW" => (0..dimension).flat_map(|i| (i..dimension).map(move |j| (i, j))).collect(),
+        "LOWER_DIAG_ROW" => (0..dimension).flat_map(|i| (0..=i).map(move |j| (i, j))).collect(),
+        other => return Err(invalid_data(format!("The {} edge weight format isn't supported", other))),
+    };
+    if pairs.len() != weights.len() {
+        return Err(invalid_data(format!("Expected {} edge weights, found {}", pairs.len(), weights.len())));
+    }
+
+    let mut distances = vec![vec![0.0; dimension]; dimension];
+    for (&(i, j), &weight) in pairs.iter().zip(weights) {
+        distances[i][j] = weight;
+        distances[j][i] = weight;
+    }
+    Ok(distances)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fitness_functions::FitnessFn;
+    use crate::genome::Permutation;
+    use crate::tsp::TspFitness;
+
+    #[test]
+    fn parse_tsplib_test() {
+        let euclidean = TspFitness::parse_tsplib(
+            "NAME : square\n\
+             TYPE : TSP\n\
+             DIMENSION : 4\n\
+             EDGE_WEIGHT_TYPE : EUC_2D\n\
+             NODE_COORD_SECTION\n\
+             1 0 0\n\
+             2 0 3\n\
+             3 4 3\n\
+             4 4 0\n\
+             EOF\n",
+        )
+        .unwrap();
+        assert_eq!(euclidean.name, "square");
+        assert_eq!(euclidean.call(&Permutation(vec![0, 1, 2, 3])), 14.0);
+        assert_eq!(euclidean.call(&Permutation(vec![0, 2, 1, 3])), 18.0);
+
+        let explicit = TspFitness::parse_tsplib(
+            "NAME: triangle\n\
+             TYPE: TSP\n\
+             DIMENSION: 3\n\
+             EDGE_WEIGHT_TYPE: EXPLICIT\n\
+             EDGE_WEIGHT_FORMAT: UPPER_ROW\n\
+             EDGE_WEIGHT_SECTION\n\
+             1 2\n\
+             3\n\
+             EOF\n",
+        )
+        .unwrap();
+        assert_eq!(explicit.call(&Permutation(vec![2, 0, 1])), 6.0);
+
+        assert!(TspFitness::parse_tsplib("TYPE: ATSP\nDIMENSION: 3\n").is_err());
+    }
+}